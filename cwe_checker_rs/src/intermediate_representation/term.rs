@@ -113,20 +113,38 @@ pub enum Jmp {
         description: String,
         return_: Option<Tid>,
     },
+    /// A multi-way jump resolved from a jump table, e.g. by a jump table recovery step.
+    ///
+    /// Unlike `BranchInd`, `Switch` retains the concrete case values together with their
+    /// resolved targets, so that downstream CFG construction can enumerate the real
+    /// successors of the block instead of falling back to a single unknown edge.
+    Switch {
+        /// The expression that is switched on.
+        index: Expression,
+        /// The concrete values of `index` that are handled explicitly, paired with the
+        /// term identifier of the `Blk` that is jumped to for that value.
+        cases: Vec<(Bitvector, Tid)>,
+        /// The `Blk` that is jumped to if `index` does not match any of the `cases`,
+        /// if such a default edge exists.
+        default: Option<Tid>,
+    },
 }
 
-/// A basic block is a sequence of `Def` instructions followed by up to two `Jmp` instructions.
+/// A basic block is a sequence of `Def` instructions followed by up to two `Jmp` instructions
+/// (or, for jump tables, a single `Jmp::Switch`).
 ///
 /// The `Def` instructions represent side-effectful operations that are executed in order when the block is entered.
 /// `Def` instructions do not affect the control flow of a program.
 ///
 /// The `Jmp` instructions represent control flow affecting operations.
-/// There can only be zero, one or two `Jmp`s:
+/// There can only be zero, one or two `Jmp`s, or exactly one `Jmp::Switch`:
 /// - Zero `Jmp`s indicate that the next execution to be executed could not be discerned.
 /// This should only happen on disassembler errors or on dead ends in the control flow graph that were deliberately inserted by the user.
-/// - If there is exactly one `Jmp`, it is required to be an unconditional jump.
+/// - If there is exactly one `Jmp` and it is not a `Jmp::Switch`, it is required to be an unconditional jump.
 /// - For two jumps, the first one has to be a conditional jump,
 /// where the second unconditional jump is only taken if the condition of the first jump evaluates to false.
+/// - A `Jmp::Switch` stands on its own and represents all resolved edges of a recovered jump table;
+/// it does not participate in the conditional/fallthrough pairing described above.
 ///
 /// Basic blocks are *single entry, single exit*, i.e. a basic block is only entered at the beginning
 /// and is only exited by the jump instructions at the end of the block.
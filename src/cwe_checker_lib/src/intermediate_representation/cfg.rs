@@ -0,0 +1,238 @@
+use super::{Blk, Jmp, Sub, Term, Tid};
+
+use std::collections::HashMap;
+
+/// A cached view of the control flow graph of a single `Sub`, computed once from the
+/// (successor-only) `Jmp` targets already present in the IR.
+///
+/// In addition to the forward edges the IR already expresses, `Cfg` exposes the reverse
+/// edges (predecessors) and an immediate-dominator map, both of which several analyses
+/// need but the IR itself does not track. Blocks that are unreachable from the entry
+/// block (`sub.blocks[0]`) have no predecessors recorded and no immediate dominator.
+pub struct Cfg {
+    /// Predecessors of every block that is reachable from the entry block.
+    predecessors: HashMap<Tid, Vec<Tid>>,
+    /// The immediate dominator of every block that is reachable from the entry block.
+    /// The entry block is its own immediate dominator.
+    idom: HashMap<Tid, Tid>,
+    /// Reverse postorder number of every reachable block, used by `dominates` and by
+    /// the fixpoint computation itself.
+    rpo_number: HashMap<Tid, usize>,
+}
+
+/// All direct intraprocedural successors of a block, as expressed by its terminating `Jmp`s.
+/// This includes the return-continuation edges of calls and the case/default edges of a
+/// `Jmp::Switch`, but not `Jmp::Return`, `Jmp::BranchInd` or an unresolved `Jmp::CallInd`,
+/// since those do not target a `Tid` known to be a block of this `Sub`.
+fn successors(blk: &Term<Blk>) -> Vec<Tid> {
+    let mut targets = Vec::new();
+    for jmp in blk.term.jmps.iter() {
+        match &jmp.term {
+            Jmp::Branch(target) => targets.push(target.clone()),
+            Jmp::CBranch { target, .. } => targets.push(target.clone()),
+            Jmp::Call {
+                return_: Some(target),
+                ..
+            }
+            | Jmp::CallInd {
+                return_: Some(target),
+                ..
+            }
+            | Jmp::CallOther {
+                return_: Some(target),
+                ..
+            } => targets.push(target.clone()),
+            Jmp::Call { return_: None, .. }
+            | Jmp::CallInd { return_: None, .. }
+            | Jmp::CallOther { return_: None, .. }
+            | Jmp::BranchInd(_)
+            | Jmp::Return(_) => (),
+            Jmp::Switch { cases, default, .. } => {
+                targets.extend(cases.iter().map(|(_, target)| target.clone()));
+                if let Some(target) = default {
+                    targets.push(target.clone());
+                }
+            }
+        }
+    }
+    targets
+}
+
+impl Cfg {
+    /// Compute the CFG of `sub`, assuming `sub.blocks[0]` is its single entry block.
+    pub fn new(sub: &Term<Sub>) -> Self {
+        let blocks_by_tid: HashMap<&Tid, &Term<Blk>> =
+            sub.term.blocks.iter().map(|blk| (&blk.tid, blk)).collect();
+
+        let Some(entry) = sub.term.blocks.first() else {
+            return Cfg {
+                predecessors: HashMap::new(),
+                idom: HashMap::new(),
+                rpo_number: HashMap::new(),
+            };
+        };
+
+        let postorder = Self::postorder(entry, &blocks_by_tid);
+        let rpo: Vec<Tid> = postorder.into_iter().rev().collect();
+        let rpo_number: HashMap<Tid, usize> = rpo
+            .iter()
+            .enumerate()
+            .map(|(number, tid)| (tid.clone(), number))
+            .collect();
+
+        let mut predecessors: HashMap<Tid, Vec<Tid>> =
+            rpo.iter().map(|tid| (tid.clone(), Vec::new())).collect();
+        for tid in rpo.iter() {
+            let blk = blocks_by_tid[tid];
+            for succ in successors(blk) {
+                if let Some(preds) = predecessors.get_mut(&succ) {
+                    preds.push(tid.clone());
+                }
+            }
+        }
+
+        let idom = Self::compute_idoms(&rpo, &rpo_number, &predecessors, &entry.tid);
+
+        Cfg {
+            predecessors,
+            idom,
+            rpo_number,
+        }
+    }
+
+    /// Depth-first postorder traversal of the blocks reachable from `entry`.
+    fn postorder(entry: &Term<Blk>, blocks_by_tid: &HashMap<&Tid, &Term<Blk>>) -> Vec<Tid> {
+        let mut visited = std::collections::HashSet::new();
+        let mut order = Vec::new();
+        let mut stack = vec![(entry.tid.clone(), successors(entry).into_iter())];
+        visited.insert(entry.tid.clone());
+
+        while let Some((tid, mut children)) = stack.pop() {
+            if let Some(child) = children.next() {
+                stack.push((tid, children));
+                if visited.insert(child.clone()) {
+                    if let Some(child_blk) = blocks_by_tid.get(&child) {
+                        stack.push((child, successors(child_blk).into_iter()));
+                    }
+                }
+            } else {
+                order.push(tid);
+            }
+        }
+        order
+    }
+
+    /// Compute immediate dominators with the iterative Cooper-Harvey-Kennedy algorithm.
+    fn compute_idoms(
+        rpo: &[Tid],
+        rpo_number: &HashMap<Tid, usize>,
+        predecessors: &HashMap<Tid, Vec<Tid>>,
+        entry: &Tid,
+    ) -> HashMap<Tid, Tid> {
+        let mut idom: HashMap<Tid, Tid> = HashMap::new();
+        idom.insert(entry.clone(), entry.clone());
+
+        let intersect = |a: &Tid, b: &Tid, idom: &HashMap<Tid, Tid>| -> Tid {
+            let mut finger1 = a.clone();
+            let mut finger2 = b.clone();
+            while finger1 != finger2 {
+                while rpo_number[&finger1] > rpo_number[&finger2] {
+                    finger1 = idom[&finger1].clone();
+                }
+                while rpo_number[&finger2] > rpo_number[&finger1] {
+                    finger2 = idom[&finger2].clone();
+                }
+            }
+            finger1
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for tid in rpo.iter() {
+                if tid == entry {
+                    continue;
+                }
+                let mut new_idom: Option<Tid> = None;
+                for pred in predecessors[tid].iter() {
+                    if !idom.contains_key(pred) {
+                        // Predecessor not yet processed in this pass (or unreachable); skip it.
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred.clone(),
+                        Some(current) => intersect(&current, pred, &idom),
+                    });
+                }
+                if let Some(new_idom) = new_idom {
+                    if idom.get(tid) != Some(&new_idom) {
+                        idom.insert(tid.clone(), new_idom);
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        idom
+    }
+
+    /// The direct predecessors of `blk`, in the order the corresponding edges were discovered.
+    /// Returns an empty slice for an unreachable or unknown block.
+    pub fn predecessors(&self, blk: &Tid) -> &[Tid] {
+        self.predecessors
+            .get(blk)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The immediate dominator of `blk`, or `None` if `blk` is unreachable from the entry block.
+    /// The entry block is its own immediate dominator, so for it the entry block itself is returned.
+    pub fn immediate_dominator(&self, blk: &Tid) -> Option<&Tid> {
+        self.idom.get(blk)
+    }
+
+    /// Whether `a` dominates `b`, i.e. every path from the entry block to `b` passes through `a`.
+    /// Every block dominates itself. Returns `false` if either block is unreachable.
+    pub fn dominates(&self, a: &Tid, b: &Tid) -> bool {
+        if a == b {
+            return self.rpo_number.contains_key(a);
+        }
+        if !self.rpo_number.contains_key(a) {
+            return false;
+        }
+        let mut current = match self.idom.get(b) {
+            Some(idom) => idom.clone(),
+            None => return false,
+        };
+        loop {
+            if &current == a {
+                return true;
+            }
+            let next = match self.idom.get(&current) {
+                Some(next) => next.clone(),
+                None => return false,
+            };
+            if &next == &current {
+                // Reached the entry block (its own immediate dominator) without finding `a`.
+                return false;
+            }
+            current = next;
+        }
+    }
+
+    /// Iterate over the dominance frontier of `blk`: the blocks that `blk` does not strictly
+    /// dominate but that have a predecessor which `blk` dominates (or is).
+    pub fn dominance_frontier(&self, blk: &Tid) -> impl Iterator<Item = &Tid> {
+        self.predecessors
+            .keys()
+            .filter(move |candidate| {
+                self.predecessors(candidate).iter().any(|pred| {
+                    (self.dominates(blk, pred) || pred == blk) && !self.strictly_dominates(blk, candidate)
+                })
+            })
+    }
+
+    fn strictly_dominates(&self, a: &Tid, b: &Tid) -> bool {
+        a != b && self.dominates(a, b)
+    }
+}
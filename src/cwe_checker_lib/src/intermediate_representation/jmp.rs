@@ -0,0 +1,80 @@
+use std::fmt;
+
+use super::{Expression, Tid};
+use crate::prelude::*;
+
+/// A `Jmp` instruction affects the control flow of a program, i.e. it may change the instruction pointer.
+/// With the exception of `CallOther`, it has no other side effects.
+///
+/// A `match` on `Jmp` that lists every variant (no `_` catch-all) turns the addition of
+/// a variant here into a compile error at that match; a `match` with a `_` arm does not.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Jmp {
+    /// A direct intraprocedural jump to the targeted `Blk` term identifier.
+    Branch(Tid),
+    /// An indirect intraprocedural jump to the address that the given expression evaluates to.
+    BranchInd(Expression),
+    /// A direct intraprocedural jump that is only taken if the condition evaluates to true (i.e. not zero).
+    CBranch {
+        /// The term identifier of the `Blk` to jump to if `condition` evaluates to true.
+        target: Tid,
+        /// The expression whose truthiness determines whether the jump is taken.
+        condition: Expression,
+    },
+    /// A direct interprocedural jump representing a subroutine call.
+    /// If `return_` is `None`, the called function does not return to its caller.
+    Call {
+        /// The term identifier of the called `Sub`.
+        target: Tid,
+        /// The term identifier of the `Blk` execution resumes at after the call returns.
+        return_: Option<Tid>,
+    },
+    /// An indirect interprocedural jump to the address `target` evaluates to, representing a subroutine call.
+    /// If `return_` is `None`, the called function is believed to not return to its caller.
+    CallInd {
+        /// The expression computing the address of the called subroutine.
+        target: Expression,
+        /// The term identifier of the `Blk` execution resumes at after the call returns.
+        return_: Option<Tid>,
+    },
+    /// An indirect interprocedural jump indicating a return from a subroutine.
+    Return(Expression),
+    /// This instruction is used for all side effects that are not representable by other instructions
+    /// or not supported by the disassembler, e.g. syscalls and other interrupts.
+    CallOther {
+        /// A textual description of the side effect, used to match for and handle known cases (e.g. syscalls).
+        description: String,
+        /// The term identifier of the `Blk` execution is assumed to resume at after handling the side effect.
+        return_: Option<Tid>,
+    },
+    /// A multi-way jump resolved from a jump table, e.g. by a jump table recovery step.
+    ///
+    /// Unlike `BranchInd`, `Switch` retains the concrete case values together with their
+    /// resolved targets, so that downstream CFG construction can enumerate the real
+    /// successors of the block instead of falling back to a single unknown edge.
+    Switch {
+        /// The expression that is switched on.
+        index: Expression,
+        /// The concrete values of `index` that are handled explicitly, paired with the
+        /// term identifier of the `Blk` that is jumped to for that value.
+        cases: Vec<(Bitvector, Tid)>,
+        /// The `Blk` that is jumped to if `index` does not match any of the `cases`,
+        /// if such a default edge exists.
+        default: Option<Tid>,
+    },
+}
+
+impl fmt::Display for Jmp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Jmp::Branch(target) => write!(f, "Jmp {target}"),
+            Jmp::BranchInd(target) => write!(f, "Jmp {target}"),
+            Jmp::CBranch { target, condition } => write!(f, "If {condition} Jmp {target}"),
+            Jmp::Call { target, .. } => write!(f, "Call {target}"),
+            Jmp::CallInd { target, .. } => write!(f, "Call {target}"),
+            Jmp::Return(target) => write!(f, "Return {target}"),
+            Jmp::CallOther { description, .. } => write!(f, "CallOther: {description}"),
+            Jmp::Switch { index, .. } => write!(f, "Switch {index}"),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Intraprocedural copy- and constant-propagation normalization pass.
+//!
+//! This rewrites a block in place, substituting later uses of a variable with its
+//! known value whenever that value is itself a plain variable or a constant, using
+//! `Term<Def>::substitute_input_var`. It then folds the resulting constant
+//! subexpressions, so that e.g. `EAX + 0` collapses to `EAX` and `ZExt(const)` to a
+//! single `Expression::Const`, which in turn lets patterns like `check_for_zero_extension`
+//! be eliminated outright. The pass is idempotent and does not change the semantics
+//! of the block, only the `recursion_depth` of its expressions and the number of
+//! dead temporaries remaining for the heavier analyses to clean up.
+//!
+//! The substitution map is reset at every block boundary; extending this to
+//! extended basic blocks (i.e. propagating along a block with a single predecessor)
+//! is left as future work.
+
+use crate::intermediate_representation::*;
+
+use std::collections::HashMap;
+
+/// Run copy- and constant-propagation over `blk`, rewriting it in place.
+pub fn propagate_copies_and_constants(blk: &mut Term<Blk>) {
+    let mut substitutions: HashMap<Variable, Expression> = HashMap::new();
+
+    for def in blk.term.defs.iter_mut() {
+        for (input_var, replacement) in substitutions.iter() {
+            def.substitute_input_var(input_var, replacement);
+        }
+        fold_constants_in_def(&mut def.term);
+
+        match &def.term {
+            Def::Assign { var, value } if is_copy_or_constant(value) => {
+                substitutions.retain(|_, replacement| !expression_reads(replacement, var));
+                substitutions.insert(var.clone(), value.clone());
+            }
+            Def::Assign { var, .. } | Def::Load { var, .. } => {
+                substitutions.remove(var);
+                substitutions.retain(|_, replacement| !expression_reads(replacement, var));
+            }
+            Def::Store { .. } => {
+                // A store can only invalidate a substitution whose replacement reads
+                // memory, and the only replacements ever recorded (see the `Assign`
+                // arm above) are plain variable copies and constants, neither of
+                // which does - so a store never needs to drop anything here.
+            }
+        }
+    }
+
+    for jmp in blk.term.jmps.iter_mut() {
+        for (input_var, replacement) in substitutions.iter() {
+            substitute_input_var_in_jmp(&mut jmp.term, input_var, replacement);
+        }
+        fold_constants_in_jmp(&mut jmp.term);
+    }
+}
+
+/// A value that is safe to propagate forward: a plain variable (a copy) or a constant.
+fn is_copy_or_constant(expr: &Expression) -> bool {
+    matches!(expr, Expression::Var(_) | Expression::Const(_))
+}
+
+/// Whether `expr` reads `var`, used to invalidate substitutions whose replacement
+/// expression is itself about to become stale.
+fn expression_reads(expr: &Expression, var: &Variable) -> bool {
+    matches!(expr, Expression::Var(other) if other == var)
+}
+
+fn substitute_input_var_in_jmp(jmp: &mut Jmp, input_var: &Variable, replacement: &Expression) {
+    match jmp {
+        Jmp::BranchInd(target) => target.substitute_input_var(input_var, replacement),
+        Jmp::CBranch { condition, .. } => condition.substitute_input_var(input_var, replacement),
+        Jmp::CallInd { target, .. } => target.substitute_input_var(input_var, replacement),
+        Jmp::Return(value) => value.substitute_input_var(input_var, replacement),
+        Jmp::Switch { index, .. } => index.substitute_input_var(input_var, replacement),
+        Jmp::Branch(_) | Jmp::Call { .. } | Jmp::CallOther { .. } => (),
+    }
+}
+
+fn fold_constants_in_def(def: &mut Def) {
+    match def {
+        Def::Assign { value, .. } => fold_constants(value),
+        Def::Load { address, .. } => fold_constants(address),
+        Def::Store { address, value } => {
+            fold_constants(address);
+            fold_constants(value);
+        }
+    }
+}
+
+fn fold_constants_in_jmp(jmp: &mut Jmp) {
+    match jmp {
+        Jmp::BranchInd(target) => fold_constants(target),
+        Jmp::CBranch { condition, .. } => fold_constants(condition),
+        Jmp::CallInd { target, .. } => fold_constants(target),
+        Jmp::Return(value) => fold_constants(value),
+        Jmp::Switch { index, .. } => fold_constants(index),
+        Jmp::Branch(_) | Jmp::Call { .. } | Jmp::CallOther { .. } => (),
+    }
+}
+
+/// Recursively fold constant subexpressions of `expr`, including additive/bitwise
+/// identities like `x + 0`, `x - 0` and `x | 0`, which commonly appear once a
+/// substituted addend turns out to be zero.
+fn fold_constants(expr: &mut Expression) {
+    match expr {
+        Expression::BinOp { op, lhs, rhs } => {
+            fold_constants(lhs);
+            fold_constants(rhs);
+            if let (Expression::Const(lhs_const), Expression::Const(rhs_const)) = (&**lhs, &**rhs) {
+                if is_safe_to_fold(*op, lhs_const, rhs_const) {
+                    *expr = Expression::Const(lhs_const.bin_op(*op, rhs_const));
+                }
+            } else if is_identity_operand(*op, rhs) {
+                let lhs = (**lhs).clone();
+                *expr = lhs;
+            } else if matches!(op, BinOpType::IntAdd | BinOpType::IntOr | BinOpType::IntXor)
+                && is_identity_operand(*op, lhs)
+            {
+                let rhs = (**rhs).clone();
+                *expr = rhs;
+            }
+        }
+        Expression::UnOp { op, arg } => {
+            fold_constants(arg);
+            if let Expression::Const(arg_const) = &**arg {
+                *expr = Expression::Const(arg_const.un_op(*op));
+            }
+        }
+        Expression::Cast { op, size, arg } => {
+            fold_constants(arg);
+            if let Expression::Const(arg_const) = &**arg {
+                *expr = Expression::Const(arg_const.cast(*op, *size));
+            }
+        }
+        Expression::Subpiece {
+            low_byte,
+            size,
+            arg,
+        } => {
+            fold_constants(arg);
+            if let Expression::Const(arg_const) = &**arg {
+                *expr = Expression::Const(arg_const.subpiece(*low_byte, *size));
+            }
+        }
+        Expression::Var(_) | Expression::Const(_) => (),
+    }
+}
+
+/// Whether folding `lhs op rhs` into a single constant is safe, i.e. it cannot panic
+/// or silently fabricate a value for an operation the CPU would itself trap on.
+/// After propagation, both operands of a division/remainder or shift can turn out to
+/// be constants the source program never actually combines this way (e.g. a divisor
+/// of `0` reachable only through a dead branch) - folding those is not semantics
+/// preserving, so they are left unfolded instead.
+fn is_safe_to_fold(op: BinOpType, lhs: &Bitvector, rhs: &Bitvector) -> bool {
+    match op {
+        BinOpType::IntDiv | BinOpType::IntSDiv | BinOpType::IntRem | BinOpType::IntSRem => {
+            !rhs.is_zero()
+        }
+        BinOpType::IntLeftShift | BinOpType::IntRightShift | BinOpType::IntSRightShift => {
+            let bit_width = u64::from(lhs.bytesize()) * 8;
+            matches!(rhs.try_to_u64(), Ok(amount) if amount < bit_width)
+        }
+        _ => true,
+    }
+}
+
+/// Whether `operand` is the identity element for `op`, i.e. `x op operand == x`
+/// (for commutative `op`s, this is checked on either side by the caller).
+fn is_identity_operand(op: BinOpType, operand: &Expression) -> bool {
+    match (op, operand) {
+        (BinOpType::IntAdd | BinOpType::IntSub | BinOpType::IntOr | BinOpType::IntXor, Expression::Const(c)) => {
+            c.is_zero()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr, variable};
+
+    #[test]
+    fn propagates_copies_and_folds_identities() {
+        let copy_def = Term {
+            tid: Tid::new("copy_tmp"),
+            term: Def::Assign {
+                var: variable!("tmp:4"),
+                value: expr!("EAX:4"),
+            },
+        };
+        let add_zero_def = Term {
+            tid: Tid::new("add_zero"),
+            term: Def::Assign {
+                var: variable!("EBX:4"),
+                value: expr!("tmp:4 + 0:4"),
+            },
+        };
+        let mut blk = Term {
+            tid: Tid::new("blk"),
+            term: Blk {
+                defs: vec![copy_def, add_zero_def],
+                jmps: Vec::new(),
+            },
+        };
+
+        propagate_copies_and_constants(&mut blk);
+
+        assert_eq!(
+            blk.term.defs[1].term,
+            Def::Assign {
+                var: variable!("EBX:4"),
+                value: expr!("EAX:4"),
+            }
+        );
+    }
+
+    #[test]
+    fn does_not_fold_division_by_a_propagated_zero_constant() {
+        let zero_def = Term {
+            tid: Tid::new("zero_tid"),
+            term: Def::Assign {
+                var: variable!("ECX:4"),
+                value: Expression::Const(Bitvector::from_u64(0).into_resize_unsigned(ByteSize::new(4))),
+            },
+        };
+        let divide_by_zero_def = Term {
+            tid: Tid::new("div_tid"),
+            term: Def::Assign {
+                var: variable!("EAX:4"),
+                value: Expression::BinOp {
+                    op: BinOpType::IntDiv,
+                    lhs: Box::new(expr!("EBX:4")),
+                    rhs: Box::new(Expression::Var(variable!("ECX:4"))),
+                },
+            },
+        };
+        let mut blk = Term {
+            tid: Tid::new("blk"),
+            term: Blk {
+                defs: vec![zero_def, divide_by_zero_def],
+                jmps: Vec::new(),
+            },
+        };
+
+        // Must not panic: ECX propagates to a constant 0 divisor, and folding
+        // `EBX / 0` into a single constant would either panic or fabricate a value
+        // for an operation the CPU itself would trap on.
+        propagate_copies_and_constants(&mut blk);
+
+        assert_eq!(
+            blk.term.defs[1].term,
+            Def::Assign {
+                var: variable!("EAX:4"),
+                value: Expression::BinOp {
+                    op: BinOpType::IntDiv,
+                    lhs: Box::new(expr!("EBX:4")),
+                    rhs: Box::new(Expression::Const(
+                        Bitvector::from_u64(0).into_resize_unsigned(ByteSize::new(4))
+                    )),
+                },
+            }
+        );
+    }
+}
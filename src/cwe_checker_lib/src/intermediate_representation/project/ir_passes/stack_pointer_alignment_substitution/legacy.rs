@@ -6,47 +6,191 @@
 use crate::intermediate_representation::*;
 use crate::utils::log::LogMessage;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use anyhow::{anyhow, Result};
 use apint::ApInt;
 
+/// The expected stack alignment of a target architecture, looked up by the Ghidra
+/// `cpu_architecture` string instead of being hardcoded into the substitution logic.
+/// `over_alignment_allowed` covers architectures/ABIs where a function is free to
+/// align more strictly than the default, e.g. for SIMD-heavy code on x86.
+#[derive(Debug, Clone, Copy)]
+struct AlignmentRule {
+    default_alignment: i64,
+    over_alignment_allowed: bool,
+}
+
+/// Known targets and their expected stack alignment. Adding a new architecture is a
+/// matter of adding a row here, not editing the substitution control flow.
+const ALIGNMENT_TABLE: &[(&str, AlignmentRule)] = &[
+    (
+        "x86_32",
+        AlignmentRule {
+            default_alignment: 16,
+            over_alignment_allowed: true,
+        },
+    ),
+    (
+        "x86_64",
+        AlignmentRule {
+            default_alignment: 16,
+            over_alignment_allowed: true,
+        },
+    ),
+    (
+        "arm32",
+        AlignmentRule {
+            default_alignment: 4,
+            over_alignment_allowed: true,
+        },
+    ),
+    (
+        "aarch64",
+        AlignmentRule {
+            default_alignment: 16,
+            over_alignment_allowed: true,
+        },
+    ),
+    (
+        "mips",
+        AlignmentRule {
+            default_alignment: 8,
+            over_alignment_allowed: false,
+        },
+    ),
+    (
+        "mips64",
+        AlignmentRule {
+            default_alignment: 16,
+            over_alignment_allowed: false,
+        },
+    ),
+    (
+        "ppc",
+        AlignmentRule {
+            default_alignment: 8,
+            over_alignment_allowed: false,
+        },
+    ),
+    (
+        "ppc64",
+        AlignmentRule {
+            default_alignment: 16,
+            over_alignment_allowed: false,
+        },
+    ),
+    (
+        "riscv32",
+        AlignmentRule {
+            default_alignment: 16,
+            over_alignment_allowed: false,
+        },
+    ),
+    (
+        "riscv64",
+        AlignmentRule {
+            default_alignment: 16,
+            over_alignment_allowed: false,
+        },
+    ),
+];
+
+/// Look up the alignment rule for `cpu_architecture`. Returns `None` for an unknown
+/// architecture, in which case callers skip the "Unexpected alignment" sanity check
+/// rather than falsely flagging every alignment operation against a made-up default.
+fn alignment_rule(cpu_architecture: &str) -> Option<AlignmentRule> {
+    ALIGNMENT_TABLE
+        .iter()
+        .find(|(arch, _)| *arch == cpu_architecture)
+        .map(|(_, rule)| *rule)
+}
+
+/// Registers known, by a preceding local constant propagation (see
+/// [`update_known_constants`]), to currently hold a given constant.
+type KnownConstants = HashMap<Variable, Bitvector>;
+
+/// Resolve `expr` to a concrete constant, either because it already is one or
+/// because it is a register `known_constants` currently tracks as holding one.
+///
+/// This is what lets the analysis see through a compiler materializing an alignment
+/// mask or addend into a scratch register first, e.g. `mov eax, 0xFFFFFFF0; and esp,
+/// eax`, instead of only recognizing the immediate form `and esp, 0xFFFFFFF0`.
+fn resolve_constant(expr: &Expression, known_constants: &KnownConstants) -> Option<Bitvector> {
+    match expr {
+        Expression::Const(constant) => Some(constant.clone()),
+        Expression::Var(var) => known_constants.get(var).cloned(),
+        _ => None,
+    }
+}
+
+/// Whether `bitmask` is a valid alignment mask under `rule`: either it aligns to
+/// exactly the architecture's default, or, if the architecture permits
+/// over-alignment, to a stricter (larger, power-of-two) boundary. A mask that
+/// aligns more *weakly* than the default is always unexpected.
+fn matches_alignment_rule(bitmask: &Bitvector, rule: AlignmentRule) -> bool {
+    let default_mask =
+        ApInt::from_i64(-rule.default_alignment).into_resize_signed(bitmask.bytesize());
+    if bitmask == &default_mask {
+        return true;
+    }
+    // A stricter mask clears every bit the default mask clears, and then some, so
+    // its 1-bits are a subset of the default mask's 1-bits.
+    rule.over_alignment_allowed && &bitmask.bin_op(BinOpType::IntAnd, &default_mask) == bitmask
+}
+
 /// Substitutes AND operation by SUB operation with calculated constants.
 ///
-/// Constants are derived by a journaled stackpointer value and the bitmask
-/// provided by the operation.
+/// Constants are derived by a journaled stackpointer value and the bitmask provided
+/// by the operation. The bitmask operand does not need to be a literal constant
+/// itself, as long as `known_constants` can resolve it to one.
+///
+/// All arithmetic is done on `Bitvector`s at their native width rather than by
+/// truncating through `i64`, so a bitmask wider than 64 bits (e.g. on a 128-bit
+/// pointer IR) is handled exactly instead of panicking or silently wrapping.
 fn substitute(
     exp: &mut Expression,
-    expected_alignment: i64,
-    journaled_sp: &mut i64,
+    rule: Option<AlignmentRule>,
+    journaled_sp: &Bitvector,
     tid: Tid,
+    known_constants: &KnownConstants,
 ) -> Vec<LogMessage> {
     let mut log: Vec<LogMessage> = vec![];
 
     if let Expression::BinOp { op, lhs, rhs } = exp {
-        match (&**lhs, &**rhs) {
-            (Expression::Var(sp), Expression::Const(bitmask))
-            | (Expression::Const(bitmask), Expression::Var(sp)) => {
+        let resolved = if let Expression::Var(sp) = &**lhs {
+            resolve_constant(rhs, known_constants).map(|bitmask| (sp.clone(), bitmask))
+        } else {
+            None
+        }
+        .or_else(|| {
+            if let Expression::Var(sp) = &**rhs {
+                resolve_constant(lhs, known_constants).map(|bitmask| (sp.clone(), bitmask))
+            } else {
+                None
+            }
+        });
+
+        match resolved {
+            Some((sp, bitmask)) => {
                 if let BinOpType::IntAnd = op {
-                    if ApInt::try_to_i64(&ApInt::into_negate(bitmask.clone())).unwrap()
-                        != expected_alignment
-                    {
-                        log.push(LogMessage::new_info("Unexpected alignment").location(tid));
+                    let bitmask = bitmask.into_resize_unsigned(journaled_sp.bytesize());
+                    if let Some(rule) = rule {
+                        if !matches_alignment_rule(&bitmask, rule) {
+                            log.push(LogMessage::new_info("Unexpected alignment").location(tid));
+                        }
                     }
-                    let offset =
-                        *journaled_sp - (*journaled_sp & bitmask.clone().try_to_i64().unwrap());
-                    let sp = sp.clone();
+                    let masked = journaled_sp.bin_op(BinOpType::IntAnd, &bitmask);
+                    let offset = journaled_sp.bin_op(BinOpType::IntSub, &masked);
                     *op = BinOpType::IntSub;
 
-                    *rhs = Box::new(Expression::Const(
-                        (ApInt::from_i64(offset)).into_resize_unsigned(bitmask.bytesize()),
-                    ));
+                    *rhs = Box::new(Expression::Const(offset));
                     *lhs = Box::new(Expression::Var(sp));
                 } else {
                     log.push(LogMessage::new_info("Unsubstitutable Operation on SP").location(tid))
                 };
             }
-            _ => log.push(
+            None => log.push(
                 LogMessage::new_info(
                     "Unsubstitutable Operation on SP. Operants are not register and constant.",
                 )
@@ -60,163 +204,455 @@ fn substitute(
 }
 
 /// Updates current stackpointer value by given constant.
+///
+/// As in [`substitute`], the constant operand may be a register that
+/// `known_constants` currently resolves to, rather than a literal immediate, and the
+/// update is computed at `journaled_sp`'s own width instead of through `i64`.
 fn journal_sp_value(
-    journaled_sp: &mut i64,
-    is_plus: bool,
+    journaled_sp: &mut Bitvector,
+    op: BinOpType,
     (rhs, lhs): (&Expression, &Expression),
     sp_register: &Variable,
+    known_constants: &KnownConstants,
 ) -> Result<()> {
-    match (rhs, lhs) {
-        (Expression::Var(sp), Expression::Const(constant))
-        | (Expression::Const(constant), Expression::Var(sp)) => {
-            if sp == sp_register {
-                match is_plus {
-                    true => *journaled_sp += constant.try_to_i64().unwrap(),
-                    false => *journaled_sp -= constant.try_to_i64().unwrap(),
-                }
-                Ok(())
-            } else {
-                Err(anyhow!("Input not stackpointer register and constant."))
-            }
+    let constant = if matches!(rhs, Expression::Var(sp) if sp == sp_register) {
+        resolve_constant(lhs, known_constants)
+    } else if matches!(lhs, Expression::Var(sp) if sp == sp_register) {
+        resolve_constant(rhs, known_constants)
+    } else {
+        None
+    };
+
+    match constant {
+        Some(constant) => {
+            let constant = constant.into_resize_unsigned(journaled_sp.bytesize());
+            *journaled_sp = journaled_sp.bin_op(op, &constant);
+            Ok(())
         }
-        _ => Err(anyhow!("Input not register and constant.")),
+        None => Err(anyhow!("Input not stackpointer register and constant.")),
     }
 }
 
-/// Returns the tid of the target of the first Jmp::Branch of the provided
-/// block.
-fn get_first_branch_tid(blk: &Term<Blk>) -> Option<&Tid> {
-    if let Some(jmp) = blk.term.jmps.first() {
-        if let Jmp::Branch(jump_to_blk) = &jmp.term {
-            return Some(jump_to_blk);
+/// Update `known_constants` with the effect of a single `def`, so that it reflects
+/// "the constant each register is currently known to hold" immediately after `def`
+/// executes.
+///
+/// This is a purely local, block-scoped constant propagation: a register is recorded
+/// when it is assigned a literal constant, and forgotten as soon as it is assigned
+/// anything else (including a `Load`), since this analysis does not track memory.
+fn update_known_constants(known_constants: &mut KnownConstants, def: &Def) {
+    match def {
+        Def::Assign {
+            var,
+            value: Expression::Const(constant),
+        } => {
+            known_constants.insert(var.clone(), constant.clone());
+        }
+        Def::Assign { var, .. } | Def::Load { var, .. } => {
+            known_constants.remove(var);
         }
+        Def::Store { .. } => (),
     }
-    None
 }
 
-/// Returns the index of the first block with non-empty defs.
-/// Blocks are iterated according by considering their first `Jmp::Branch`.
-/// If a block is revisited, `None` is returned.
-fn get_first_blk_with_defs(sub: &Sub) -> Option<usize> {
-    let blocks = &sub.blocks;
-    if let Some(start_blk) = blocks.first() {
-        let mut visited = HashSet::new();
-        let mut blk = start_blk;
-
-        'search_loop: while blk.term.defs.is_empty() {
-            if let Some(target_tid) = get_first_branch_tid(blk) {
-                if !visited.contains(&blk.tid) {
-                    visited.insert(&blk.tid);
-
-                    // try find this target
-                    for (index, target_blk) in blocks.iter().enumerate() {
-                        if &target_blk.tid == target_tid {
-                            if !target_blk.term.defs.is_empty() {
-                                return Some(index);
-                            } else {
-                                // continue with new block
-                                blk = target_blk;
-                                continue 'search_loop;
+/// The offset of the stack pointer relative to the start of the subroutine at a given
+/// program point, or `None` ("top" of the lattice) if it is not known to be a single
+/// concrete value, e.g. because two predecessors disagree or because a def was found
+/// that this analysis cannot reason about.
+///
+/// Kept as a width-tagged `Bitvector` rather than a bare `i64`, so offsets on
+/// architectures with pointers or alignment masks wider than 64 bits are represented
+/// exactly instead of being truncated.
+type Offset = Option<Bitvector>;
+
+/// The standard flat-lattice join: equal concrete offsets stay concrete, anything else
+/// (including either side already being unknown) collapses to unknown.
+fn join_offsets(a: &Offset, b: &Offset) -> Offset {
+    match (a, b) {
+        (Some(x), Some(y)) if x == y => Some(x.clone()),
+        _ => None,
+    }
+}
+
+/// All direct intraprocedural successors of a block that this analysis follows.
+///
+/// This mirrors the `Blk` invariant of up to two `Jmp`s (a `CBranch` followed by its
+/// unconditional fallthrough), plus the return-continuation edges of calls, since a
+/// callee does not alter the caller's stack pointer value on a balanced call/return.
+/// `BranchInd`, `Return` and calls without a known return site are dead ends for this
+/// analysis, identical to how the single-block version of this pass already ignored
+/// everything it could not directly follow.
+fn successors(blk: &Term<Blk>) -> Vec<Tid> {
+    blk.term
+        .jmps
+        .iter()
+        .flat_map(|jmp| match &jmp.term {
+            Jmp::Branch(target) | Jmp::CBranch { target, .. } => vec![target.clone()],
+            Jmp::Call {
+                return_: Some(target),
+                ..
+            }
+            | Jmp::CallInd {
+                return_: Some(target),
+                ..
+            }
+            | Jmp::CallOther {
+                return_: Some(target),
+                ..
+            } => vec![target.clone()],
+            Jmp::Switch { cases, default, .. } => cases
+                .iter()
+                .map(|(_, target)| target.clone())
+                .chain(default.iter().cloned())
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Simulate the effect of `blk` on the journaled stack pointer offset, without
+/// mutating the IR, so that the entry/exit offset fixpoint can be computed before any
+/// substitution is committed. Mirrors the decision tree of [`substitute`] and
+/// [`journal_sp_value`], so that the later mutating pass reaches the same exit offset
+/// for every block whose entry offset turned out to be concrete, with one difference:
+/// an `AND` is only accepted here if its register operand is `stack_pointer_register`,
+/// whereas [`substitute`] accepts an `AND` of any register with a constant.
+fn simulate_block_offset(
+    blk: &Term<Blk>,
+    entry_offset: Offset,
+    stack_pointer_register: &Variable,
+) -> Offset {
+    let mut journaled_sp = entry_offset?;
+    let mut known_constants: KnownConstants = KnownConstants::new();
+    for def in blk.term.defs.iter() {
+        if let Def::Assign { var, value } = &def.term {
+            if var == stack_pointer_register {
+                match value {
+                    Expression::BinOp {
+                        op: op @ (BinOpType::IntAdd | BinOpType::IntSub),
+                        lhs,
+                        rhs,
+                    } => {
+                        journal_sp_value(
+                            &mut journaled_sp,
+                            *op,
+                            (lhs, rhs),
+                            stack_pointer_register,
+                            &known_constants,
+                        )
+                        .ok()?;
+                    }
+                    Expression::BinOp {
+                        op: BinOpType::IntAnd,
+                        lhs,
+                        rhs,
+                    } => {
+                        let resolves = if let Expression::Var(sp) = &**lhs {
+                            sp == stack_pointer_register
+                                && resolve_constant(rhs, &known_constants).is_some()
+                        } else {
+                            false
+                        } || if let Expression::Var(sp) = &**rhs {
+                            sp == stack_pointer_register
+                                && resolve_constant(lhs, &known_constants).is_some()
+                        } else {
+                            false
+                        };
+                        if !resolves {
+                            return None;
+                        }
+                        // Substitution keeps the journaled offset unchanged, same as
+                        // the mutating pass below: the rewritten expression computes
+                        // the same concrete value, it is just phrased as a SUB.
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        update_known_constants(&mut known_constants, &def.term);
+    }
+    Some(journaled_sp)
+}
+
+/// Walk `blk`'s defs starting from `entry_offset`, rewriting every alignment `AND` on
+/// `stack_pointer_register` it finds using the offset known at that point, and return
+/// the resulting exit offset together with any log messages produced. If `entry_offset`
+/// is unknown, the block is left untouched and reported as unknown as well.
+fn process_block(
+    blk: &mut Term<Blk>,
+    entry_offset: Offset,
+    stack_pointer_register: &Variable,
+    rule: Option<AlignmentRule>,
+) -> (Offset, Vec<LogMessage>) {
+    let mut logs: Vec<LogMessage> = vec![];
+    let Some(mut journaled_sp) = entry_offset else {
+        return (None, logs);
+    };
+    let mut known_constants: KnownConstants = KnownConstants::new();
+
+    for def in blk.term.defs.iter_mut() {
+        if let Def::Assign { var, value } = &mut def.term {
+            if var == stack_pointer_register {
+                if let Expression::BinOp { op, lhs, rhs } = value {
+                    match op {
+                        BinOpType::IntAdd | BinOpType::IntSub => {
+                            if journal_sp_value(
+                                &mut journaled_sp,
+                                *op,
+                                (lhs, rhs),
+                                stack_pointer_register,
+                                &known_constants,
+                            )
+                            .is_err()
+                            {
+                                return (None, logs);
+                            }
+                        }
+                        _ => {
+                            let mut msg = substitute(
+                                value,
+                                rule,
+                                &journaled_sp,
+                                def.tid.clone(),
+                                &known_constants,
+                            );
+                            let unsubstitutable = msg
+                                .iter()
+                                .any(|msg| msg.text.contains("Unsubstitutable Operation on SP"));
+                            logs.append(&mut msg);
+                            if unsubstitutable {
+                                return (None, logs);
                             }
                         }
                     }
-                    // did not find target
-                    return None;
                 } else {
-                    // busy loop
-                    return None;
+                    logs.push(
+                        LogMessage::new_info("Unexpected assignment on SP")
+                            .location(def.tid.clone()),
+                    );
+                    return (None, logs);
                 }
-            } else {
-                // did not find branch in block
-                return None;
             }
         }
-        // first block was not empty
-        return Some(0);
+        update_known_constants(&mut known_constants, &def.term);
+    }
+
+    (Some(journaled_sp), logs)
+}
+
+/// Compute the fixpoint of entry offsets for every block of `sub`, following
+/// [`successors`] edges, starting from offset `0` at `sub.blocks[0]`. Unreached blocks
+/// keep no entry in the returned map.
+fn fixpoint_entry_offsets(
+    sub: &Term<Sub>,
+    stack_pointer_register: &Variable,
+) -> HashMap<Tid, Offset> {
+    let Some(entry_block) = sub.term.blocks.first() else {
+        return HashMap::new();
+    };
+
+    let zero = ApInt::from_u64(0).into_resize_unsigned(stack_pointer_register.size);
+
+    let mut entry_offset: HashMap<Tid, Offset> = HashMap::new();
+    entry_offset.insert(entry_block.tid.clone(), Some(zero));
+
+    let mut worklist: VecDeque<Tid> = VecDeque::new();
+    let mut queued: HashSet<Tid> = HashSet::new();
+    worklist.push_back(entry_block.tid.clone());
+    queued.insert(entry_block.tid.clone());
+
+    while let Some(tid) = worklist.pop_front() {
+        queued.remove(&tid);
+        let Some(blk) = sub.term.blocks.iter().find(|blk| blk.tid == tid) else {
+            continue;
+        };
+        let offset = entry_offset.get(&tid).cloned().unwrap_or(None);
+        let exit_offset = simulate_block_offset(blk, offset, stack_pointer_register);
+
+        for succ in successors(blk) {
+            let joined = match entry_offset.get(&succ) {
+                Some(existing) => join_offsets(existing, &exit_offset),
+                None => exit_offset.clone(),
+            };
+            let changed = entry_offset.get(&succ) != Some(&joined);
+            entry_offset.insert(succ.clone(), joined);
+            if changed && queued.insert(succ.clone()) {
+                worklist.push_back(succ);
+            }
+        }
+    }
+
+    entry_offset
+}
+
+/// A recognized dynamic-realignment prologue: the stack pointer is aligned down by
+/// an `AND`, its pre-alignment value has been saved into `original_sp_register`
+/// beforehand, and/or its post-alignment value is copied into `aligned_sp_register`
+/// (typically the frame-pointer register) right afterward.
+///
+/// Downstream stack-variable recovery cannot simply reason about `[sp + k]` once
+/// such a prologue has run, since the nominal frame is now only reachable relative to
+/// whichever of these two registers actually survives; recording the relationship
+/// here is what lets that analysis still resolve `[original_sp_register + k]` or
+/// `[aligned_sp_register + k]` accesses to the right stack slots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredAlignment {
+    pub sub: Tid,
+    pub blk: Tid,
+    /// The bitmask the prologue ANDs the stack pointer with.
+    pub alignment_mask: Bitvector,
+    /// The register the pre-alignment stack pointer was copied into, if any.
+    pub original_sp_register: Option<Variable>,
+    /// The register the post-alignment stack pointer was copied into, if any.
+    pub aligned_sp_register: Option<Variable>,
+}
+
+/// Look for the dynamic-realignment prologue pattern in `blk`: an `AND` on
+/// `stack_pointer_register` with a (possibly register-held) constant mask, preceded
+/// by a plain copy of the stack pointer into another register and/or followed
+/// immediately by a plain copy of the stack pointer into another register.
+///
+/// Returns `None` if `blk` contains no alignment `AND` on `stack_pointer_register`,
+/// or if neither a pre- nor a post-alignment copy is present (a plain alignment with
+/// no frame-pointer handoff does not need this extra bookkeeping; [`substitute`]
+/// already rewrites it).
+fn detect_realignment_prologue(
+    blk: &Term<Blk>,
+    stack_pointer_register: &Variable,
+) -> Option<RecoveredAlignment> {
+    let mut known_constants: KnownConstants = KnownConstants::new();
+    for (index, def) in blk.term.defs.iter().enumerate() {
+        let Def::Assign {
+            var,
+            value:
+                Expression::BinOp {
+                    op: BinOpType::IntAnd,
+                    lhs,
+                    rhs,
+                },
+        } = &def.term
+        else {
+            update_known_constants(&mut known_constants, &def.term);
+            continue;
+        };
+        if var != stack_pointer_register {
+            update_known_constants(&mut known_constants, &def.term);
+            continue;
+        }
+        let alignment_mask = if matches!(&**lhs, Expression::Var(sp) if sp == stack_pointer_register)
+        {
+            resolve_constant(rhs, &known_constants)
+        } else if matches!(&**rhs, Expression::Var(sp) if sp == stack_pointer_register) {
+            resolve_constant(lhs, &known_constants)
+        } else {
+            None
+        };
+        let Some(alignment_mask) = alignment_mask else {
+            return None;
+        };
+
+        let original_sp_register =
+            blk.term.defs[..index]
+                .iter()
+                .rev()
+                .find_map(|def| match &def.term {
+                    Def::Assign {
+                        var,
+                        value: Expression::Var(sp),
+                    } if sp == stack_pointer_register => Some(var.clone()),
+                    _ => None,
+                });
+        let aligned_sp_register = blk.term.defs.get(index + 1).and_then(|def| match &def.term {
+            Def::Assign {
+                var,
+                value: Expression::Var(sp),
+            } if sp == stack_pointer_register => Some(var.clone()),
+            _ => None,
+        });
+
+        if original_sp_register.is_none() && aligned_sp_register.is_none() {
+            return None;
+        }
+        return Some(RecoveredAlignment {
+            sub: Tid::new(""),
+            blk: blk.tid.clone(),
+            alignment_mask,
+            original_sp_register,
+            aligned_sp_register,
+        });
     }
     None
 }
 
 /// Substitutes logical AND on the stackpointer register by SUB.
 /// Expressions are changed to use constants w.r.t the provided bit mask.
+///
+/// Unlike the original, block-local version of this analysis, the journaled offset is
+/// now computed as a fixpoint over the whole CFG of every `Sub`: it is propagated along
+/// `Jmp::Branch`/`Jmp::CBranch` edges instead of only inspecting the single block
+/// returned by a "first block with defs" search, so a prologue that branches or loops
+/// before aligning the stack pointer is still handled. Blocks reachable from two
+/// predecessors that disagree on the concrete offset are reported as unknown via a
+/// `LogMessage` instead of being silently skipped.
+///
+/// The expected alignment is looked up per `cpu_architecture` in [`ALIGNMENT_TABLE`]
+/// instead of being hardcoded, and a dynamic-realignment prologue (the aligned stack
+/// pointer handed off to a frame-pointer register, with the original value saved
+/// elsewhere) is reported as a [`RecoveredAlignment`] per function so that downstream
+/// stack-variable recovery can still make sense of the function's frame.
+///
+/// Like the other normalization passes in `ir_passes`, this is a library function with
+/// no caller inside this crate; the pass-pipeline driver that runs it over a `Project`
+/// and routes its two results on - the `LogMessage`s into the run's diagnostics, the
+/// `RecoveredAlignment`s into stack-variable recovery - lives outside this crate. The
+/// two `Vec`s are returned separately, not merged into one, because only the former is
+/// ever displayed to a user; conflating them would force stack-variable recovery to
+/// filter `LogMessage`s back out of its input.
 pub fn substitute_and_on_stackpointer(
     program: &mut Program,
     stack_pointer_register: &Variable,
     cpu_architecture: &str,
-) -> Vec<LogMessage> {
-    // for sanity check
-    let sp_alignment = match cpu_architecture {
-        "x86_32" => 16,
-        "x86_64" => 16,
-        "arm32" => 4,
-        _ => 0,
-    };
+) -> (Vec<LogMessage>, Vec<RecoveredAlignment>) {
+    let rule = alignment_rule(cpu_architecture);
 
     let mut logs: Vec<LogMessage> = vec![];
+    let mut recovered_alignments: Vec<RecoveredAlignment> = vec![];
 
-    'sub_loop: for f in program.functions_mut() {
-        let journaled_sp: &mut i64 = &mut 0;
-        if let Some(index) = get_first_blk_with_defs(&f.term) {
-            let blk = &mut f.term.blocks[index];
-            for def in blk.term.defs.iter_mut() {
-                if let Def::Assign { var, value } = &mut def.term {
-                    if var == stack_pointer_register {
-                        if let Expression::BinOp { op, lhs, rhs } = value {
-                            match op {
-                                BinOpType::IntAdd => {
-                                    if journal_sp_value(
-                                        journaled_sp,
-                                        true,
-                                        (lhs, rhs),
-                                        stack_pointer_register,
-                                    )
-                                    .is_err()
-                                    {
-                                        continue 'sub_loop;
-                                    }
-                                }
-                                BinOpType::IntSub => {
-                                    if journal_sp_value(
-                                        journaled_sp,
-                                        false,
-                                        (lhs, rhs),
-                                        stack_pointer_register,
-                                    )
-                                    .is_err()
-                                    {
-                                        continue 'sub_loop;
-                                    }
-                                }
-                                _ => {
-                                    let mut msg = substitute(
-                                        value,
-                                        sp_alignment,
-                                        journaled_sp,
-                                        def.tid.clone(),
-                                    );
-                                    logs.append(&mut msg);
-                                    if logs.iter().any(|msg| {
-                                        msg.text.contains("Unsubstitutable Operation on SP")
-                                    }) {
-                                        // Lost track of SP
-                                        continue 'sub_loop;
-                                    }
-                                }
-                            }
-                        } else {
-                            logs.push(
-                                LogMessage::new_info("Unexpected assignment on SP")
-                                    .location(def.tid.clone()),
-                            );
-                            continue 'sub_loop;
-                        }
-                    }
+    for sub in program.functions_mut() {
+        let entry_offsets = fixpoint_entry_offsets(sub, stack_pointer_register);
+
+        for blk in sub.term.blocks.iter_mut() {
+            if let Some(mut recovered) = detect_realignment_prologue(blk, stack_pointer_register) {
+                recovered.sub = sub.tid.clone();
+                logs.push(
+                    LogMessage::new_info(&format!(
+                        "Recovered dynamic stack realignment: original sp in {:?}, aligned sp in {:?}",
+                        recovered.original_sp_register, recovered.aligned_sp_register
+                    ))
+                    .location(recovered.blk.clone()),
+                );
+                recovered_alignments.push(recovered);
+            }
+
+            let entry_offset = entry_offsets.get(&blk.tid).cloned().unwrap_or(None);
+            if entry_offset.is_none() {
+                if entry_offsets.contains_key(&blk.tid) {
+                    logs.push(
+                        LogMessage::new_info(
+                            "Stack pointer offset unknown at block entry (differing offsets merged, or lost track of SP in a predecessor)",
+                        )
+                        .location(blk.tid.clone()),
+                    );
                 }
+                continue;
             }
+            let (_, mut block_logs) =
+                process_block(blk, entry_offset, stack_pointer_register, rule);
+            logs.append(&mut block_logs);
         }
     }
 
-    logs
+    (logs, recovered_alignments)
 }
 
 #[cfg(test)]
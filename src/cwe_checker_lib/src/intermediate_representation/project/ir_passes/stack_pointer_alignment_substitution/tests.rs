@@ -0,0 +1,185 @@
+use super::*;
+use crate::variable;
+
+/// Build the `AND`-on-stackpointer expression a compiler emits for a stack alignment,
+/// e.g. `ESP = ESP & 0xFFFFFFF0` for a 16-byte alignment on a 4-byte register.
+fn alignment_and(sp: &Variable, mask: Bitvector) -> Expression {
+    Expression::BinOp {
+        op: BinOpType::IntAnd,
+        lhs: Box::new(Expression::Var(sp.clone())),
+        rhs: Box::new(Expression::Const(mask)),
+    }
+}
+
+/// `-alignment` resized to `size`, i.e. the bitmask a compiler uses to align down to
+/// a multiple of `alignment` (which must be a power of two).
+fn alignment_mask(alignment: i64, size: ByteSize) -> Bitvector {
+    ApInt::from_i64(-alignment).into_resize_signed(size)
+}
+
+fn rule_expecting(default_alignment: i64) -> AlignmentRule {
+    AlignmentRule {
+        default_alignment,
+        over_alignment_allowed: false,
+    }
+}
+
+fn substitutes_alignment(sp: &Variable, alignment: i64, journaled_sp_value: i64) {
+    let mask = alignment_mask(alignment, sp.size);
+    let mut expr = alignment_and(sp, mask);
+    let journaled_sp = ApInt::from_i64(journaled_sp_value).into_resize_unsigned(sp.size);
+
+    let logs = substitute(
+        &mut expr,
+        Some(rule_expecting(alignment)),
+        &journaled_sp,
+        Tid::new("and_instr"),
+        &KnownConstants::new(),
+    );
+    assert!(logs.is_empty(), "unexpected alignment logs: {:?}", logs);
+
+    let expected_offset = journaled_sp_value - (journaled_sp_value & -alignment);
+    match expr {
+        Expression::BinOp {
+            op: BinOpType::IntSub,
+            rhs,
+            ..
+        } => {
+            assert_eq!(
+                *rhs,
+                Expression::Const(ApInt::from_i64(expected_offset).into_resize_unsigned(sp.size))
+            );
+        }
+        other => panic!("expected substitution to rewrite the AND into a SUB, got {:?}", other),
+    }
+}
+
+#[test]
+fn substitutes_16_byte_alignment() {
+    substitutes_alignment(&variable!("ESP:4"), 16, 100);
+}
+
+#[test]
+fn substitutes_32_byte_alignment() {
+    substitutes_alignment(&variable!("ESP:4"), 32, 50);
+}
+
+#[test]
+fn substitutes_64_byte_alignment_on_a_64_bit_register() {
+    substitutes_alignment(&variable!("RSP:8"), 64, 12345);
+}
+
+/// A mask wider than 64 bits (e.g. a 128-bit pointer IR) must not panic, even though
+/// its value does not fit into an `i64`.
+#[test]
+fn does_not_panic_on_a_mask_wider_than_64_bits() {
+    let sp = variable!("SP:16");
+    let mask = alignment_mask(16, sp.size);
+    let mut expr = alignment_and(&sp, mask);
+    let journaled_sp = ApInt::from_i64(256).into_resize_unsigned(sp.size);
+
+    let logs = substitute(
+        &mut expr,
+        Some(rule_expecting(16)),
+        &journaled_sp,
+        Tid::new("and_instr"),
+        &KnownConstants::new(),
+    );
+    assert!(logs.is_empty(), "unexpected alignment logs: {:?}", logs);
+}
+
+#[test]
+fn reports_unexpected_alignment() {
+    let sp = variable!("ESP:4");
+    let mask = alignment_mask(16, sp.size);
+    let mut expr = alignment_and(&sp, mask);
+    let journaled_sp = ApInt::from_i64(100).into_resize_unsigned(sp.size);
+
+    // The pass is told to expect 32-byte alignment, but the mask aligns to 16 bytes.
+    let logs = substitute(
+        &mut expr,
+        Some(rule_expecting(32)),
+        &journaled_sp,
+        Tid::new("and_instr"),
+        &KnownConstants::new(),
+    );
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].text.contains("Unexpected alignment"));
+}
+
+#[test]
+fn allows_over_alignment_when_permitted() {
+    let sp = variable!("ESP:4");
+    // Default is 16-byte alignment, but this function over-aligns to 32 bytes for
+    // SIMD locals, which `over_alignment_allowed` permits.
+    let mask = alignment_mask(32, sp.size);
+    let mut expr = alignment_and(&sp, mask);
+    let journaled_sp = ApInt::from_i64(100).into_resize_unsigned(sp.size);
+
+    let rule = AlignmentRule {
+        default_alignment: 16,
+        over_alignment_allowed: true,
+    };
+    let logs = substitute(
+        &mut expr,
+        Some(rule),
+        &journaled_sp,
+        Tid::new("and_instr"),
+        &KnownConstants::new(),
+    );
+    assert!(logs.is_empty(), "unexpected alignment logs: {:?}", logs);
+}
+
+#[test]
+fn detects_realignment_prologue_with_frame_pointer_handoff() {
+    let sp = variable!("ESP:4");
+    let ebp = variable!("EBP:4");
+    let mask = alignment_mask(16, sp.size);
+
+    let save_original = Term {
+        tid: Tid::new("save_original_sp"),
+        term: Def::Assign {
+            var: ebp.clone(),
+            value: Expression::Var(sp.clone()),
+        },
+    };
+    let align = Term {
+        tid: Tid::new("align_sp"),
+        term: Def::Assign {
+            var: sp.clone(),
+            value: alignment_and(&sp, mask.clone()),
+        },
+    };
+    let blk = Term {
+        tid: Tid::new("blk"),
+        term: Blk {
+            defs: vec![save_original, align],
+            jmps: Vec::new(),
+        },
+    };
+
+    let recovered = detect_realignment_prologue(&blk, &sp).unwrap();
+    assert_eq!(recovered.alignment_mask, mask);
+    assert_eq!(recovered.original_sp_register, Some(ebp));
+    assert_eq!(recovered.aligned_sp_register, None);
+}
+
+#[test]
+fn journal_sp_value_resolves_addend_held_in_a_scratch_register() {
+    let sp = variable!("ESP:4");
+    let scratch = variable!("EAX:4");
+    let mut known_constants = KnownConstants::new();
+    known_constants.insert(scratch.clone(), ApInt::from_i64(8).into_resize_unsigned(sp.size));
+
+    let mut journaled_sp = ApInt::from_i64(100).into_resize_unsigned(sp.size);
+    journal_sp_value(
+        &mut journaled_sp,
+        BinOpType::IntSub,
+        (&Expression::Var(sp.clone()), &Expression::Var(scratch)),
+        &sp,
+        &known_constants,
+    )
+    .unwrap();
+
+    assert_eq!(journaled_sp, ApInt::from_i64(92).into_resize_unsigned(sp.size));
+}
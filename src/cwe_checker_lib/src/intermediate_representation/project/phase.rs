@@ -0,0 +1,237 @@
+//! Typed IR phases and an invariant-checking validator for [`Project`].
+
+use crate::intermediate_representation::*;
+
+use anyhow::{anyhow, Error};
+use std::collections::HashSet;
+
+/// The phase a piece of IR is in, mirroring how far it has progressed through
+/// the normalization and optimization pipeline.
+///
+/// Some invariants only hold from a certain phase onward (e.g. `CallOther` terms
+/// with an unknown `description` are only disallowed once the IR has been normalized).
+/// `Project::validate` is parameterized by the phase so that callers can check exactly
+/// the invariants that are expected to hold at the point the IR is in.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum IrPhase {
+    /// IR as produced directly by the Ghidra frontend, before any normalization.
+    /// Only the most basic structural invariants are guaranteed to hold.
+    Raw,
+    /// IR after normalization, e.g. known `CallOther` side effects have been resolved
+    /// and unreachable blocks have been removed.
+    Normalized,
+    /// IR after optimization passes (e.g. constant propagation) have run.
+    Optimized,
+}
+
+impl Project {
+    /// Check that `self` satisfies the structural invariants that are documented on
+    /// the IR types but not enforced by the type system, for the given `phase`.
+    ///
+    /// Returns all violations found rather than stopping at the first one, so that
+    /// malformed IR coming out of the Ghidra frontend can be diagnosed in one pass.
+    pub fn validate(&self, phase: IrPhase) -> Result<(), Vec<Error>> {
+        let mut errors = Vec::new();
+
+        let known_block_tids: HashSet<&Tid> = self
+            .program
+            .term
+            .subs
+            .iter()
+            .flat_map(|sub| sub.term.blocks.iter().map(|blk| &blk.tid))
+            .collect();
+        let known_sub_tids: HashSet<&Tid> = self
+            .program
+            .term
+            .subs
+            .iter()
+            .map(|sub| &sub.tid)
+            .collect();
+
+        for sub in self.program.term.subs.iter() {
+            for blk in sub.term.blocks.iter() {
+                validate_blk(
+                    blk,
+                    &self.stack_pointer_register,
+                    &known_block_tids,
+                    &known_sub_tids,
+                    phase,
+                    &mut errors,
+                );
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Check the invariants of a single `Blk` and push any violation onto `errors`.
+fn validate_blk(
+    blk: &Term<Blk>,
+    stack_pointer_register: &Variable,
+    known_block_tids: &HashSet<&Tid>,
+    known_sub_tids: &HashSet<&Tid>,
+    phase: IrPhase,
+    errors: &mut Vec<Error>,
+) {
+    for def in blk.term.defs.iter() {
+        validate_def(def, stack_pointer_register, errors);
+    }
+
+    match blk.term.jmps.as_slice() {
+        // Zero jmps, or a single jmp (whether a plain unconditional jump or a
+        // `Jmp::Switch` standing on its own), are both allowed.
+        [] | [_] => (),
+        [first, second] => {
+            if !matches!(first.term, Jmp::CBranch { .. }) {
+                errors.push(anyhow!(
+                    "Blk {}: of two jmps the first one must be a `Jmp::CBranch`",
+                    blk.tid
+                ));
+            }
+            if matches!(second.term, Jmp::CBranch { .. } | Jmp::Switch { .. }) {
+                errors.push(anyhow!(
+                    "Blk {}: the fallthrough jmp must be an unconditional jump",
+                    blk.tid
+                ));
+            }
+        }
+        _ => errors.push(anyhow!(
+            "Blk {}: more than two jmps (and not a single `Jmp::Switch`)",
+            blk.tid
+        )),
+    }
+
+    for jmp in blk.term.jmps.iter() {
+        validate_jmp(jmp, known_block_tids, known_sub_tids, phase, errors);
+    }
+}
+
+/// Check size and pointer-size invariants on a single `Def`.
+fn validate_def(def: &Term<Def>, stack_pointer_register: &Variable, errors: &mut Vec<Error>) {
+    match &def.term {
+        Def::Load { address, .. } => {
+            // `var`'s size is by definition the number of bytes `Load` reads from memory
+            // (see the doc comment on `Def::Load`), so there is no independent size to
+            // check it against; only the address's pointer size is checked below.
+            if expression_byte_size(address) != Some(stack_pointer_register.size) {
+                errors.push(anyhow!(
+                    "Def {}: address of `Load` does not match the pointer size of `stack_pointer_register`",
+                    def.tid
+                ));
+            }
+        }
+        Def::Store { address, .. } => {
+            // Likewise, `value`'s size is by definition the number of bytes written.
+            if expression_byte_size(address) != Some(stack_pointer_register.size) {
+                errors.push(anyhow!(
+                    "Def {}: address of `Store` does not match the pointer size of `stack_pointer_register`",
+                    def.tid
+                ));
+            }
+        }
+        Def::Assign { var, value } => {
+            if let Some(value_size) = expression_byte_size(value) {
+                if value_size != var.size {
+                    errors.push(anyhow!(
+                        "Def {}: size of `var` ({} bytes) does not match the size of `value` ({} bytes)",
+                        def.tid,
+                        var.size,
+                        value_size
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Best-effort byte size of an expression, where determinable without a full
+/// type-checking pass. Used both to check that addresses match the pointer size
+/// and that an `Assign`'s output `var` matches the size of the value assigned to it.
+fn expression_byte_size(expr: &Expression) -> Option<ByteSize> {
+    match expr {
+        Expression::Var(var) => Some(var.size),
+        Expression::Const(value) => Some(value.bytesize()),
+        Expression::BinOp { op, lhs, .. }
+            if matches!(
+                op,
+                BinOpType::IntAdd
+                    | BinOpType::IntSub
+                    | BinOpType::IntMult
+                    | BinOpType::IntDiv
+                    | BinOpType::IntSDiv
+                    | BinOpType::IntRem
+                    | BinOpType::IntSRem
+                    | BinOpType::IntAnd
+                    | BinOpType::IntOr
+                    | BinOpType::IntXor
+                    | BinOpType::IntLeftShift
+                    | BinOpType::IntRightShift
+                    | BinOpType::IntSRightShift
+            ) =>
+        {
+            // These operators keep the width of their left operand; the comparison,
+            // carry/borrow and boolean operators instead produce a fixed-width flag
+            // and are intentionally left unmatched (falling through to `None`).
+            expression_byte_size(lhs)
+        }
+        Expression::UnOp { arg, .. } => expression_byte_size(arg),
+        Expression::Cast { size, .. } => Some(*size),
+        Expression::Subpiece { size, .. } => Some(*size),
+        _ => None,
+    }
+}
+
+/// Check that every target `Tid` referenced by a `Jmp` resolves to an existing `Blk` or `Sub`,
+/// and any invariants that only apply from a given `phase` onward.
+fn validate_jmp(
+    jmp: &Term<Jmp>,
+    known_block_tids: &HashSet<&Tid>,
+    known_sub_tids: &HashSet<&Tid>,
+    phase: IrPhase,
+    errors: &mut Vec<Error>,
+) {
+    let mut check_block_target = |target: &Tid| {
+        if !known_block_tids.contains(target) {
+            errors.push(anyhow!(
+                "Jmp {}: target {} does not resolve to a known `Blk`",
+                jmp.tid,
+                target
+            ));
+        }
+    };
+    match &jmp.term {
+        Jmp::Branch(target) => check_block_target(target),
+        Jmp::CBranch { target, .. } => check_block_target(target),
+        Jmp::Call { target, .. } => {
+            if !known_sub_tids.contains(target) {
+                errors.push(anyhow!(
+                    "Jmp {}: call target {} does not resolve to a known `Sub`",
+                    jmp.tid,
+                    target
+                ));
+            }
+        }
+        Jmp::Switch { cases, default, .. } => {
+            for (_, target) in cases.iter() {
+                check_block_target(target);
+            }
+            if let Some(target) = default {
+                check_block_target(target);
+            }
+        }
+        Jmp::CallOther { description, .. } => {
+            if phase >= IrPhase::Normalized && description == "unknown" {
+                errors.push(anyhow!(
+                    "Jmp {}: `CallOther` with unknown description survived normalization",
+                    jmp.tid
+                ));
+            }
+        }
+        Jmp::BranchInd(_) | Jmp::CallInd { .. } | Jmp::Return(_) => (),
+    }
+}
@@ -0,0 +1,962 @@
+//! A round-trippable textual format for a whole [`Project`].
+//!
+//! The [`std::fmt::Display`] impls of individual terms (e.g. [`Def`] and [`Jmp`]) are not
+//! enough to dump a whole `Program`/`Sub`/`Blk`, and there was no way to go the other way and reconstruct
+//! IR from text. This module adds both directions: [`print_project`] renders every `Sub`
+//! with its name and [`Tid`], every `Blk` labeled by its `Tid`'s address, the ordered
+//! `defs`/`jmps` each still carrying their own `Tid`, and every `ExternSymbol` with its
+//! calling convention and `Arg` positions; [`parse_project`] reads that text back into a
+//! `Project`, preserving `Tid.id` and `Tid.address` exactly.
+//!
+//! The format is intentionally its own small grammar (think of it as a restricted,
+//! line-oriented relative of MIR's pretty-printer, where every statement keeps a stable
+//! source location) rather than a reuse of any ad hoc `Display` impls the individual
+//! expression types may already have, so that printing and parsing are guaranteed to
+//! stay in lockstep. It is meant for regression fixtures and for hand-editing small CFGs,
+//! not as a replacement for the Ghidra frontend's own (de)serialization.
+
+use super::{
+    Arg, BinOpType, Blk, ByteSize, CastOpType, Def, Expression, ExternSymbol, Jmp, Program,
+    Project, Sub, Term, Tid, UnOpType, Variable,
+};
+use crate::prelude::*;
+
+use anyhow::{anyhow, Context, Error};
+use apint::ApInt;
+
+/// Render `project` in the textual format described in the module documentation.
+pub fn print_project(project: &Project) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("arch {}\n", project.cpu_architecture));
+    out.push_str(&format!("sp {}\n", print_variable(&project.stack_pointer_register)));
+    for entry in project.program.term.entry_points.iter() {
+        out.push_str(&format!("entry {}\n", print_tid(entry)));
+    }
+    out.push_str(&format!("program {} {{\n", print_tid(&project.program.tid)));
+    for sub in project.program.term.subs.iter() {
+        print_sub(sub, &mut out);
+    }
+    for symbol in project.program.term.extern_symbols.iter() {
+        print_extern_symbol(symbol, &mut out);
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn print_sub(sub: &Term<Sub>, out: &mut String) {
+    out.push_str(&format!("  sub {} \"{}\" {{\n", print_tid(&sub.tid), sub.term.name));
+    for blk in sub.term.blocks.iter() {
+        print_blk(blk, out);
+    }
+    out.push_str("  }\n");
+}
+
+fn print_blk(blk: &Term<Blk>, out: &mut String) {
+    out.push_str(&format!("    blk {} {{\n", print_tid(&blk.tid)));
+    for def in blk.term.defs.iter() {
+        out.push_str(&format!("      {}: {}\n", print_tid(&def.tid), print_def(&def.term)));
+    }
+    for jmp in blk.term.jmps.iter() {
+        out.push_str(&format!("      {}: {}\n", print_tid(&jmp.tid), print_jmp(&jmp.term)));
+    }
+    out.push_str("    }\n");
+}
+
+fn print_extern_symbol(symbol: &ExternSymbol, out: &mut String) {
+    let cconv = symbol.calling_convention.as_deref().unwrap_or("?");
+    let params = symbol
+        .parameters
+        .iter()
+        .map(print_arg)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let returns = symbol
+        .return_values
+        .iter()
+        .map(print_arg)
+        .collect::<Vec<_>>()
+        .join(", ");
+    out.push_str(&format!(
+        "  extern {} \"{}\" cconv={} params=({}) returns=({}) no_return={}\n",
+        print_tid(&symbol.tid), symbol.name, cconv, params, returns, symbol.no_return
+    ));
+}
+
+/// Render `tid` with its `address` appended, so that parsing it back (via [`parse_tid`])
+/// recovers a `Tid` that compares equal to `tid` (`Tid` equality includes `address`).
+/// Using bare `{tid}` (id only) anywhere a `Tid` is round-tripped loses that address.
+fn print_tid(tid: &Tid) -> String {
+    format!("{tid}@{}", tid.address)
+}
+
+/// The inverse of [`print_tid`].
+fn parse_tid(s: &str) -> Tid {
+    match s.rsplit_once('@') {
+        Some((id, address)) => {
+            let mut tid = Tid::new(id);
+            tid.address = address.to_string();
+            tid
+        }
+        None => Tid::new(s),
+    }
+}
+
+fn print_arg(arg: &Arg) -> String {
+    match arg {
+        Arg::Register(var) => format!("reg({})", print_variable(var)),
+        Arg::Stack { offset, size } => format!("stack({offset}, {})", u64::from(*size)),
+    }
+}
+
+fn print_def(def: &Def) -> String {
+    match def {
+        Def::Load { var, address } => {
+            format!("{} = load {}", print_variable(var), print_expr(address))
+        }
+        Def::Store { address, value } => {
+            format!("store {} = {}", print_expr(address), print_expr(value))
+        }
+        Def::Assign { var, value } => format!("{} = {}", print_variable(var), print_expr(value)),
+    }
+}
+
+fn print_jmp(jmp: &Jmp) -> String {
+    match jmp {
+        Jmp::Branch(target) => format!("goto {}", print_tid(target)),
+        Jmp::BranchInd(target) => format!("goto *{}", print_expr(target)),
+        Jmp::CBranch { target, condition } => {
+            format!("if {} goto {}", print_expr(condition), print_tid(target))
+        }
+        Jmp::Call { target, return_ } => match return_ {
+            Some(r) => format!("call {} returns_to {}", print_tid(target), print_tid(r)),
+            None => format!("call {} no_return", print_tid(target)),
+        },
+        Jmp::CallInd { target, return_ } => match return_ {
+            Some(r) => format!("call *{} returns_to {}", print_expr(target), print_tid(r)),
+            None => format!("call *{} no_return", print_expr(target)),
+        },
+        Jmp::Return(value) => format!("return {}", print_expr(value)),
+        Jmp::CallOther {
+            description,
+            return_,
+        } => match return_ {
+            Some(r) => format!("call_other \"{description}\" returns_to {}", print_tid(r)),
+            None => format!("call_other \"{description}\" no_return"),
+        },
+        Jmp::Switch {
+            index,
+            cases,
+            default,
+        } => {
+            let cases = cases
+                .iter()
+                .map(|(value, target)| format!("{} -> {}", print_bitvector(value), print_tid(target)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            match default {
+                Some(d) => format!("switch {} {{{cases}}} default {}", print_expr(index), print_tid(d)),
+                None => format!("switch {} {{{cases}}}", print_expr(index)),
+            }
+        }
+    }
+}
+
+fn print_variable(var: &Variable) -> String {
+    if var.is_temp {
+        format!("${}:{}", var.name, u64::from(var.size))
+    } else {
+        format!("{}:{}", var.name, u64::from(var.size))
+    }
+}
+
+fn print_bitvector(value: &Bitvector) -> String {
+    format!("{:#x}:{}", value, value.bytesize())
+}
+
+fn print_expr(expr: &Expression) -> String {
+    match expr {
+        Expression::Var(var) => print_variable(var),
+        Expression::Const(value) => print_bitvector(value),
+        Expression::BinOp { op, lhs, rhs } => {
+            format!("({} {} {})", print_expr(lhs), print_bin_op(*op), print_expr(rhs))
+        }
+        Expression::UnOp { op, arg } => format!("({} {})", print_un_op(*op), print_expr(arg)),
+        Expression::Cast { op, size, arg } => {
+            format!("({}:{} {})", print_cast_op(*op), u64::from(*size), print_expr(arg))
+        }
+        Expression::Subpiece {
+            low_byte,
+            size,
+            arg,
+        } => format!(
+            "subpiece({}, {}, {})",
+            u64::from(*low_byte),
+            u64::from(*size),
+            print_expr(arg)
+        ),
+    }
+}
+
+fn print_bin_op(op: BinOpType) -> String {
+    match op {
+        BinOpType::Piece => "piece".to_string(),
+        BinOpType::IntEqual => "==".to_string(),
+        BinOpType::IntNotEqual => "!=".to_string(),
+        BinOpType::IntLess => "<".to_string(),
+        BinOpType::IntSLess => "s<".to_string(),
+        BinOpType::IntLessEqual => "<=".to_string(),
+        BinOpType::IntSLessEqual => "s<=".to_string(),
+        BinOpType::IntAdd => "+".to_string(),
+        BinOpType::IntSub => "-".to_string(),
+        BinOpType::IntCarry => "carry".to_string(),
+        BinOpType::IntSCarry => "scarry".to_string(),
+        BinOpType::IntSBorrow => "sborrow".to_string(),
+        BinOpType::IntMult => "*".to_string(),
+        BinOpType::IntDiv => "/".to_string(),
+        BinOpType::IntSDiv => "s/".to_string(),
+        BinOpType::IntRem => "%".to_string(),
+        BinOpType::IntSRem => "s%".to_string(),
+        BinOpType::IntLeftShift => "<<".to_string(),
+        BinOpType::IntRightShift => ">>".to_string(),
+        BinOpType::IntSRightShift => "s>>".to_string(),
+        BinOpType::IntAnd => "&".to_string(),
+        BinOpType::IntOr => "|".to_string(),
+        BinOpType::IntXor => "^".to_string(),
+        BinOpType::BoolAnd => "&&".to_string(),
+        BinOpType::BoolOr => "||".to_string(),
+        BinOpType::BoolXor => "^^".to_string(),
+        BinOpType::FloatEqual => "f==".to_string(),
+        BinOpType::FloatNotEqual => "f!=".to_string(),
+        BinOpType::FloatLess => "f<".to_string(),
+        BinOpType::FloatLessEqual => "f<=".to_string(),
+        BinOpType::FloatAdd => "f+".to_string(),
+        BinOpType::FloatSub => "f-".to_string(),
+        BinOpType::FloatMult => "f*".to_string(),
+        BinOpType::FloatDiv => "f/".to_string(),
+    }
+}
+
+fn print_un_op(op: UnOpType) -> String {
+    match op {
+        UnOpType::IntNegate => "IntNegate".to_string(),
+        UnOpType::Int2Comp => "Int2Comp".to_string(),
+        UnOpType::BoolNegate => "BoolNegate".to_string(),
+        UnOpType::FloatNegate => "FloatNegate".to_string(),
+        UnOpType::FloatAbs => "FloatAbs".to_string(),
+        UnOpType::FloatSqrt => "FloatSqrt".to_string(),
+        UnOpType::FloatCeil => "FloatCeil".to_string(),
+        UnOpType::FloatFloor => "FloatFloor".to_string(),
+        UnOpType::FloatRound => "FloatRound".to_string(),
+        UnOpType::FloatNaN => "FloatNaN".to_string(),
+    }
+}
+
+fn print_cast_op(op: CastOpType) -> String {
+    match op {
+        CastOpType::IntZExt => "IntZExt".to_string(),
+        CastOpType::IntSExt => "IntSExt".to_string(),
+        CastOpType::Int2Float => "Int2Float".to_string(),
+        CastOpType::Float2Float => "Float2Float".to_string(),
+        CastOpType::Trunc => "Trunc".to_string(),
+        CastOpType::PopCount => "PopCount".to_string(),
+    }
+}
+
+/// Parse `text` (as produced by [`print_project`]) back into a `Project`.
+///
+/// `Tid.id` and `Tid.address` are preserved exactly as printed, so diffing two dumps of
+/// the same `Project` taken before/after a transformation is meaningful.
+pub fn parse_project(text: &str) -> Result<Project, Error> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    let mut cpu_architecture = None;
+    let mut stack_pointer_register = None;
+    let mut entry_points = Vec::new();
+    let mut subs = Vec::new();
+    let mut extern_symbols = Vec::new();
+    let mut program_tid = None;
+
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("arch ") {
+            cpu_architecture = Some(rest.to_string());
+        } else if let Some(rest) = line.strip_prefix("sp ") {
+            stack_pointer_register = Some(parse_variable(rest)?);
+        } else if let Some(rest) = line.strip_prefix("entry ") {
+            entry_points.push(parse_tid(rest));
+        } else if let Some(rest) = line.strip_prefix("program ") {
+            let tid = rest.trim_end_matches('{').trim();
+            program_tid = Some(parse_tid(tid));
+        } else if let Some(rest) = line.strip_prefix("sub ") {
+            subs.push(parse_sub(rest, &mut lines)?);
+        } else if let Some(rest) = line.strip_prefix("extern ") {
+            extern_symbols.push(parse_extern_symbol(rest)?);
+        } else if line == "}" {
+            continue;
+        } else {
+            return Err(anyhow!("unrecognized top-level line: '{line}'"));
+        }
+    }
+
+    Ok(Project {
+        program: Term {
+            tid: program_tid.ok_or_else(|| anyhow!("missing 'program' line"))?,
+            term: Program {
+                subs,
+                extern_symbols,
+                entry_points,
+            },
+        },
+        cpu_architecture: cpu_architecture.ok_or_else(|| anyhow!("missing 'arch' line"))?,
+        stack_pointer_register: stack_pointer_register
+            .ok_or_else(|| anyhow!("missing 'sp' line"))?,
+    })
+}
+
+fn parse_sub<'a>(
+    header: &str,
+    lines: &mut impl Iterator<Item = &'a str>,
+) -> Result<Term<Sub>, Error> {
+    let (tid_str, rest) = header
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("malformed sub header: '{header}'"))?;
+    let name = rest
+        .trim()
+        .trim_end_matches('{')
+        .trim()
+        .trim_matches('"')
+        .to_string();
+    let tid = parse_tid(tid_str);
+
+    let mut blocks = Vec::new();
+    for line in lines.by_ref() {
+        if line == "}" {
+            return Ok(Term {
+                tid,
+                term: Sub { name, blocks },
+            });
+        }
+        if let Some(rest) = line.strip_prefix("blk ") {
+            blocks.push(parse_blk(rest, lines)?);
+        } else {
+            return Err(anyhow!("unexpected line inside sub '{name}': '{line}'"));
+        }
+    }
+    Err(anyhow!("unterminated sub '{name}'"))
+}
+
+fn parse_blk<'a>(
+    header: &str,
+    lines: &mut impl Iterator<Item = &'a str>,
+) -> Result<Term<Blk>, Error> {
+    let (tid_str, _) = header
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("malformed blk header: '{header}'"))?;
+    let tid = parse_tid(tid_str);
+
+    let mut defs = Vec::new();
+    let mut jmps = Vec::new();
+    for line in lines {
+        if line == "}" {
+            return Ok(Term {
+                tid,
+                term: Blk { defs, jmps },
+            });
+        }
+        let (stmt_tid, body) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("statement missing a leading tid: '{line}'"))?;
+        let stmt_tid = parse_tid(stmt_tid.trim());
+        let body = body.trim();
+        if is_jmp_statement(body) {
+            jmps.push(Term {
+                tid: stmt_tid,
+                term: parse_jmp(body)?,
+            });
+        } else {
+            defs.push(Term {
+                tid: stmt_tid,
+                term: parse_def(body)?,
+            });
+        }
+    }
+    Err(anyhow!("unterminated blk"))
+}
+
+fn is_jmp_statement(body: &str) -> bool {
+    // Every `Def` prints in an assignment form containing the literal substring " = "
+    // (`var = load addr`, `store addr = value`, or `var = value`, see `print_def`
+    // above), while the `Jmp` forms do not - every `=` that `print_expr` emits is part
+    // of a longer operator such as `==`, `!=` or `<=`, and none of `goto`/`if .. goto`/
+    // `return`/`call*`/`switch` use `=` otherwise. Checking for " = " (rather than
+    // whether `body` starts with a jmp keyword) also correctly routes a `Def::Assign`
+    // whose output variable happens to be named e.g. `return_addr:8` or `call_target:8`.
+    // A `call_other` whose description itself contains " = " is parsed as a `Def`.
+    !body.contains(" = ")
+}
+
+fn parse_def(body: &str) -> Result<Def, Error> {
+    if let Some(rest) = body.strip_prefix("store ") {
+        let (address, value) = rest
+            .split_once(" = ")
+            .ok_or_else(|| anyhow!("malformed store: '{body}'"))?;
+        return Ok(Def::Store {
+            address: parse_expr(address.trim())?,
+            value: parse_expr(value.trim())?,
+        });
+    }
+    let (var, rhs) = body
+        .split_once(" = ")
+        .ok_or_else(|| anyhow!("malformed def: '{body}'"))?;
+    let var = parse_variable(var.trim())?;
+    if let Some(address) = rhs.trim().strip_prefix("load ") {
+        Ok(Def::Load {
+            var,
+            address: parse_expr(address.trim())?,
+        })
+    } else {
+        Ok(Def::Assign {
+            var,
+            value: parse_expr(rhs.trim())?,
+        })
+    }
+}
+
+fn parse_jmp(body: &str) -> Result<Jmp, Error> {
+    if let Some(rest) = body.strip_prefix("goto *") {
+        return Ok(Jmp::BranchInd(parse_expr(rest)?));
+    }
+    if let Some(rest) = body.strip_prefix("goto ") {
+        return Ok(Jmp::Branch(parse_tid(rest.trim())));
+    }
+    if let Some(rest) = body.strip_prefix("if ") {
+        let (condition, target) = rest
+            .split_once(" goto ")
+            .ok_or_else(|| anyhow!("malformed cbranch: '{body}'"))?;
+        return Ok(Jmp::CBranch {
+            target: parse_tid(target.trim()),
+            condition: parse_expr(condition.trim())?,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("return ") {
+        return Ok(Jmp::Return(parse_expr(rest)?));
+    }
+    if let Some(rest) = body.strip_prefix("call_other ") {
+        let (description, tail) = parse_quoted(rest)?;
+        return Ok(Jmp::CallOther {
+            description,
+            return_: parse_return_clause(tail.trim())?,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("call *") {
+        let (target, tail) = split_call_target(rest)?;
+        return Ok(Jmp::CallInd {
+            target: parse_expr(target)?,
+            return_: parse_return_clause(tail)?,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("call ") {
+        let (target, tail) = split_call_target(rest)?;
+        return Ok(Jmp::Call {
+            target: parse_tid(target),
+            return_: parse_return_clause(tail)?,
+        });
+    }
+    if let Some(rest) = body.strip_prefix("switch ") {
+        return parse_switch(rest);
+    }
+    Err(anyhow!("unrecognized jmp: '{body}'"))
+}
+
+fn parse_switch(body: &str) -> Result<Jmp, Error> {
+    let (index, tail) = body
+        .split_once('{')
+        .ok_or_else(|| anyhow!("malformed switch: '{body}'"))?;
+    let (cases, tail) = tail
+        .split_once('}')
+        .ok_or_else(|| anyhow!("malformed switch: '{body}'"))?;
+    let cases = cases
+        .split(',')
+        .map(str::trim)
+        .filter(|case| !case.is_empty())
+        .map(|case| {
+            let (value, target) = case
+                .split_once("->")
+                .ok_or_else(|| anyhow!("malformed switch case: '{case}'"))?;
+            Ok((parse_bitvector(value.trim())?, parse_tid(target.trim())))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let default = tail
+        .trim()
+        .strip_prefix("default ")
+        .map(|d| parse_tid(d.trim()));
+    Ok(Jmp::Switch {
+        index: parse_expr(index.trim())?,
+        cases,
+        default,
+    })
+}
+
+fn split_call_target(rest: &str) -> Result<(&str, &str), Error> {
+    rest.split_once(' ')
+        .ok_or_else(|| anyhow!("call is missing its return clause: '{rest}'"))
+}
+
+fn parse_return_clause(tail: &str) -> Result<Option<Tid>, Error> {
+    if tail == "no_return" {
+        Ok(None)
+    } else if let Some(target) = tail.strip_prefix("returns_to ") {
+        Ok(Some(parse_tid(target.trim())))
+    } else {
+        Err(anyhow!("malformed call return clause: '{tail}'"))
+    }
+}
+
+fn parse_quoted(s: &str) -> Result<(String, &str), Error> {
+    let s = s.trim_start();
+    let rest = s
+        .strip_prefix('"')
+        .ok_or_else(|| anyhow!("expected a quoted string in '{s}'"))?;
+    let end = rest
+        .find('"')
+        .ok_or_else(|| anyhow!("unterminated quoted string in '{s}'"))?;
+    Ok((rest[..end].to_string(), &rest[end + 1..]))
+}
+
+fn parse_extern_symbol(header: &str) -> Result<ExternSymbol, Error> {
+    let (tid_str, rest) = header
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("malformed extern header: '{header}'"))?;
+    let (name, rest) = parse_quoted(rest)?;
+
+    let mut calling_convention = None;
+    let mut parameters = Vec::new();
+    let mut return_values = Vec::new();
+    let mut no_return = false;
+    for field in split_top_level(rest)? {
+        if let Some(value) = field.strip_prefix("cconv=") {
+            calling_convention = (value != "?").then_some(value.to_string());
+        } else if let Some(value) = field.strip_prefix("params=") {
+            parameters = parse_arg_list(value)?;
+        } else if let Some(value) = field.strip_prefix("returns=") {
+            return_values = parse_arg_list(value)?;
+        } else if let Some(value) = field.strip_prefix("no_return=") {
+            no_return = value
+                .parse()
+                .with_context(|| format!("malformed no_return flag: '{value}'"))?;
+        }
+    }
+
+    Ok(ExternSymbol {
+        tid: parse_tid(tid_str),
+        name,
+        calling_convention,
+        parameters,
+        return_values,
+        no_return,
+    })
+}
+
+fn parse_arg_list(s: &str) -> Result<Vec<Arg>, Error> {
+    let s = s.trim_start_matches('(').trim_end_matches(')');
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+    s.split("), ")
+        .map(|entry| entry.trim_end_matches(')'))
+        .map(parse_arg)
+        .collect()
+}
+
+fn parse_arg(s: &str) -> Result<Arg, Error> {
+    if let Some(rest) = s.strip_prefix("reg(") {
+        Ok(Arg::Register(parse_variable(rest)?))
+    } else if let Some(rest) = s.strip_prefix("stack(") {
+        let (offset, size) = rest
+            .split_once(", ")
+            .ok_or_else(|| anyhow!("malformed stack arg: '{s}'"))?;
+        Ok(Arg::Stack {
+            offset: offset.trim().parse().context("malformed stack offset")?,
+            size: ByteSize::new(size.trim().parse().context("malformed stack arg size")?),
+        })
+    } else {
+        Err(anyhow!("unrecognized arg: '{s}'"))
+    }
+}
+
+fn parse_variable(s: &str) -> Result<Variable, Error> {
+    let (is_temp, s) = match s.strip_prefix('$') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (name, size) = s
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("malformed variable: '{s}'"))?;
+    Ok(Variable {
+        name: name.to_string(),
+        size: ByteSize::new(size.parse().with_context(|| format!("malformed variable size: '{size}'"))?),
+        is_temp,
+    })
+}
+
+/// Parse a `print_bitvector`-formatted constant, e.g. `0xffffffff00000000:8`.
+///
+/// The hex digits are folded in one at a time at the constant's own bit width (via
+/// repeated multiply-by-16/add), rather than going through `u64::from_str_radix`,
+/// so that a value wider than 64 bits (e.g. a 16-byte constant) parses
+/// correctly instead of being silently truncated.
+fn parse_bitvector(s: &str) -> Result<Bitvector, Error> {
+    let (value, size) = s
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("malformed constant: '{s}'"))?;
+    let value = value
+        .strip_prefix("0x")
+        .ok_or_else(|| anyhow!("malformed constant: '{s}'"))?;
+    let size: u64 = size
+        .parse()
+        .with_context(|| format!("malformed constant size: '{size}'"))?;
+    let size = ByteSize::new(size);
+
+    let mut result = ApInt::from_u64(0).into_resize_unsigned(size);
+    let sixteen = ApInt::from_u64(16).into_resize_unsigned(size);
+    for digit in value.chars() {
+        let digit = digit
+            .to_digit(16)
+            .ok_or_else(|| anyhow!("malformed constant value: '{value}'"))?;
+        result = result.bin_op(BinOpType::IntMult, &sixteen);
+        result = result.bin_op(
+            BinOpType::IntAdd,
+            &ApInt::from_u64(u64::from(digit)).into_resize_unsigned(size),
+        );
+    }
+    Ok(result)
+}
+
+fn parse_expr(s: &str) -> Result<Expression, Error> {
+    let s = s.trim();
+    if let Some(rest) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        return parse_paren_expr(rest);
+    }
+    if let Some(rest) = s.strip_prefix("subpiece(") {
+        let rest = rest
+            .strip_suffix(')')
+            .ok_or_else(|| anyhow!("malformed subpiece: '{s}'"))?;
+        let mut parts = rest.splitn(3, ',');
+        let low_byte = parts
+            .next()
+            .ok_or_else(|| anyhow!("malformed subpiece: '{s}'"))?
+            .trim()
+            .parse()
+            .context("malformed subpiece low_byte")?;
+        let size = parts
+            .next()
+            .ok_or_else(|| anyhow!("malformed subpiece: '{s}'"))?
+            .trim()
+            .parse()
+            .context("malformed subpiece size")?;
+        let arg = parts
+            .next()
+            .ok_or_else(|| anyhow!("malformed subpiece: '{s}'"))?;
+        return Ok(Expression::Subpiece {
+            low_byte: ByteSize::new(low_byte),
+            size: ByteSize::new(size),
+            arg: Box::new(parse_expr(arg)?),
+        });
+    }
+    if s.contains("0x") {
+        return Ok(Expression::Const(parse_bitvector(s)?));
+    }
+    Ok(Expression::Var(parse_variable(s)?))
+}
+
+/// Parse the inside of a parenthesized binary/unary/cast expression, i.e. everything
+/// between the outermost `(` and `)` that [`parse_expr`] already stripped off.
+fn parse_paren_expr(s: &str) -> Result<Expression, Error> {
+    // A cast is printed as `op:size arg`; a unary op as `op arg`; a binary op as
+    // `lhs op rhs`. All three forms are disambiguated by splitting on top-level
+    // whitespace while respecting nested parentheses.
+    let tokens = split_top_level(s)?;
+    match tokens.as_slice() {
+        [op, arg] if op.contains(':') => {
+            let (op, size) = op
+                .split_once(':')
+                .ok_or_else(|| anyhow!("malformed cast operator: '{op}'"))?;
+            Ok(Expression::Cast {
+                op: parse_cast_op(op)?,
+                size: ByteSize::new(size.parse().context("malformed cast size")?),
+                arg: Box::new(parse_expr(arg)?),
+            })
+        }
+        [op, arg] => Ok(Expression::UnOp {
+            op: parse_un_op(op)?,
+            arg: Box::new(parse_expr(arg)?),
+        }),
+        [lhs, op, rhs] => Ok(Expression::BinOp {
+            op: parse_bin_op(op)?,
+            lhs: Box::new(parse_expr(lhs)?),
+            rhs: Box::new(parse_expr(rhs)?),
+        }),
+        _ => Err(anyhow!("malformed expression: '({s})'")),
+    }
+}
+
+/// Split `s` on whitespace, without splitting inside nested parentheses.
+fn split_top_level(s: &str) -> Result<Vec<&str>, Error> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| anyhow!("unbalanced parentheses in '{s}'"))?
+            }
+            ' ' if depth == 0 => {
+                if start < i {
+                    tokens.push(s[start..i].trim());
+                }
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    if start < s.len() {
+        tokens.push(s[start..].trim());
+    }
+    Ok(tokens.into_iter().filter(|t| !t.is_empty()).collect())
+}
+
+fn parse_bin_op(s: &str) -> Result<BinOpType, Error> {
+    Ok(match s {
+        "piece" => BinOpType::Piece,
+        "==" => BinOpType::IntEqual,
+        "!=" => BinOpType::IntNotEqual,
+        "<" => BinOpType::IntLess,
+        "s<" => BinOpType::IntSLess,
+        "<=" => BinOpType::IntLessEqual,
+        "s<=" => BinOpType::IntSLessEqual,
+        "+" => BinOpType::IntAdd,
+        "-" => BinOpType::IntSub,
+        "carry" => BinOpType::IntCarry,
+        "scarry" => BinOpType::IntSCarry,
+        "sborrow" => BinOpType::IntSBorrow,
+        "*" => BinOpType::IntMult,
+        "/" => BinOpType::IntDiv,
+        "s/" => BinOpType::IntSDiv,
+        "%" => BinOpType::IntRem,
+        "s%" => BinOpType::IntSRem,
+        "<<" => BinOpType::IntLeftShift,
+        ">>" => BinOpType::IntRightShift,
+        "s>>" => BinOpType::IntSRightShift,
+        "&" => BinOpType::IntAnd,
+        "|" => BinOpType::IntOr,
+        "^" => BinOpType::IntXor,
+        "&&" => BinOpType::BoolAnd,
+        "||" => BinOpType::BoolOr,
+        "^^" => BinOpType::BoolXor,
+        "f==" => BinOpType::FloatEqual,
+        "f!=" => BinOpType::FloatNotEqual,
+        "f<" => BinOpType::FloatLess,
+        "f<=" => BinOpType::FloatLessEqual,
+        "f+" => BinOpType::FloatAdd,
+        "f-" => BinOpType::FloatSub,
+        "f*" => BinOpType::FloatMult,
+        "f/" => BinOpType::FloatDiv,
+        other => return Err(anyhow!("unknown binary operator: '{other}'")),
+    })
+}
+
+fn parse_un_op(s: &str) -> Result<UnOpType, Error> {
+    match s {
+        "IntNegate" => Ok(UnOpType::IntNegate),
+        "Int2Comp" => Ok(UnOpType::Int2Comp),
+        "BoolNegate" => Ok(UnOpType::BoolNegate),
+        "FloatNegate" => Ok(UnOpType::FloatNegate),
+        "FloatAbs" => Ok(UnOpType::FloatAbs),
+        "FloatSqrt" => Ok(UnOpType::FloatSqrt),
+        "FloatCeil" => Ok(UnOpType::FloatCeil),
+        "FloatFloor" => Ok(UnOpType::FloatFloor),
+        "FloatRound" => Ok(UnOpType::FloatRound),
+        "FloatNaN" => Ok(UnOpType::FloatNaN),
+        other => Err(anyhow!("unknown unary operator: '{other}'")),
+    }
+}
+
+fn parse_cast_op(s: &str) -> Result<CastOpType, Error> {
+    match s {
+        "IntZExt" => Ok(CastOpType::IntZExt),
+        "IntSExt" => Ok(CastOpType::IntSExt),
+        "Int2Float" => Ok(CastOpType::Int2Float),
+        "Float2Float" => Ok(CastOpType::Float2Float),
+        "Trunc" => Ok(CastOpType::Trunc),
+        "PopCount" => Ok(CastOpType::PopCount),
+        other => Err(anyhow!("unknown cast operator: '{other}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variable;
+
+    #[test]
+    fn round_trips_a_minimal_project() {
+        let project = Project {
+            program: Term {
+                tid: Tid::new("program"),
+                term: Program {
+                    subs: vec![Term {
+                        tid: Tid::new("sub_main"),
+                        term: Sub {
+                            name: "main".to_string(),
+                            blocks: vec![Term {
+                                tid: {
+                                    let mut tid = Tid::new("blk_entry");
+                                    tid.address = "0x1000".to_string();
+                                    tid
+                                },
+                                term: Blk {
+                                    defs: vec![Term {
+                                        tid: Tid::new("def_0"),
+                                        term: Def::Assign {
+                                            var: variable!("EAX:4"),
+                                            value: Expression::Const(Bitvector::from_u64(0).into_resize_unsigned(ByteSize::new(4))),
+                                        },
+                                    }],
+                                    jmps: vec![Term {
+                                        tid: Tid::new("jmp_0"),
+                                        term: Jmp::Return(Expression::Var(variable!("EAX:4"))),
+                                    }],
+                                },
+                            }],
+                        },
+                    }],
+                    extern_symbols: Vec::new(),
+                    entry_points: vec![Tid::new("sub_main")],
+                },
+            },
+            cpu_architecture: "x86_64".to_string(),
+            stack_pointer_register: variable!("RSP:8"),
+        };
+
+        let printed = print_project(&project);
+        let parsed = parse_project(&printed).expect("round trip should parse");
+        assert_eq!(parsed, project);
+    }
+
+    /// A `Branch` target whose `Tid` carries a real (non-`UNKNOWN`) address must still
+    /// compare equal after a print/parse round trip, since `Tid` equality includes
+    /// `address`.
+    #[test]
+    fn round_trips_a_branch_target_with_a_real_address() {
+        let mut target_tid = Tid::new("blk_loop");
+        target_tid.address = "0x2000".to_string();
+
+        let blk = Term {
+            tid: target_tid.clone(),
+            term: Blk {
+                defs: Vec::new(),
+                jmps: vec![Term {
+                    tid: Tid::new("jmp_0"),
+                    term: Jmp::Branch(target_tid),
+                }],
+            },
+        };
+        let project = Project {
+            program: Term {
+                tid: Tid::new("program"),
+                term: Program {
+                    subs: vec![Term {
+                        tid: Tid::new("sub_main"),
+                        term: Sub {
+                            name: "main".to_string(),
+                            blocks: vec![blk],
+                        },
+                    }],
+                    extern_symbols: Vec::new(),
+                    entry_points: vec![Tid::new("sub_main")],
+                },
+            },
+            cpu_architecture: "x86_64".to_string(),
+            stack_pointer_register: variable!("RSP:8"),
+        };
+
+        let printed = print_project(&project);
+        let parsed = parse_project(&printed).expect("round trip should parse");
+        assert_eq!(parsed, project);
+    }
+
+    #[test]
+    fn round_trips_a_constant_wider_than_64_bits() {
+        let wide = ApInt::from_u64(0x1111_2222_3333_4444)
+            .into_resize_unsigned(ByteSize::new(16))
+            .bin_op(
+                BinOpType::IntLeftShift,
+                &ApInt::from_u64(64).into_resize_unsigned(ByteSize::new(16)),
+            )
+            .bin_op(
+                BinOpType::IntOr,
+                &ApInt::from_u64(0x5555_6666_7777_8888).into_resize_unsigned(ByteSize::new(16)),
+            );
+        let printed = print_bitvector(&wide);
+        let parsed = parse_bitvector(&printed).expect("should parse a 128-bit constant");
+        assert_eq!(parsed, wide);
+    }
+
+    /// A `Def::Assign` whose output variable name happens to start with a `Jmp`
+    /// keyword (`goto`/`call`/`return`) must still round-trip as a `Def`, not be
+    /// misparsed as a `Jmp`.
+    #[test]
+    fn round_trips_an_assign_whose_var_name_shadows_a_jmp_keyword() {
+        let blk = Term {
+            tid: Tid::new("blk_entry"),
+            term: Blk {
+                defs: vec![
+                    Term {
+                        tid: Tid::new("def_0"),
+                        term: Def::Assign {
+                            var: variable!("return_addr:8"),
+                            value: Expression::Const(
+                                Bitvector::from_u64(0).into_resize_unsigned(ByteSize::new(8)),
+                            ),
+                        },
+                    },
+                    Term {
+                        tid: Tid::new("def_1"),
+                        term: Def::Assign {
+                            var: variable!("call_target:8"),
+                            value: Expression::Var(variable!("return_addr:8")),
+                        },
+                    },
+                ],
+                jmps: vec![Term {
+                    tid: Tid::new("jmp_0"),
+                    term: Jmp::Return(Expression::Var(variable!("call_target:8"))),
+                }],
+            },
+        };
+        let project = Project {
+            program: Term {
+                tid: Tid::new("program"),
+                term: Program {
+                    subs: vec![Term {
+                        tid: Tid::new("sub_main"),
+                        term: Sub {
+                            name: "main".to_string(),
+                            blocks: vec![blk],
+                        },
+                    }],
+                    extern_symbols: Vec::new(),
+                    entry_points: vec![Tid::new("sub_main")],
+                },
+            },
+            cpu_architecture: "x86_64".to_string(),
+            stack_pointer_register: variable!("RSP:8"),
+        };
+
+        let printed = print_project(&project);
+        let parsed = parse_project(&printed).expect("round trip should parse");
+        assert_eq!(parsed, project);
+    }
+}
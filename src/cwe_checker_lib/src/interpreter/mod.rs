@@ -0,0 +1,338 @@
+//! A concrete interpreter for the IR of a single `Sub`.
+//!
+//! The interpreter executes one subroutine over an explicit machine state
+//! consisting of a register file and a byte-addressable memory, evaluating
+//! `Def`s and following `Jmp`s exactly as the disassembled machine code would.
+//! It is bounded by a maximum step count so that it always terminates, even
+//! on subroutines containing loops, and it reports `BranchInd`/`CallInd`
+//! targets that it cannot resolve as `Error::Unsupported` instead of guessing.
+//!
+//! This is intended for dynamic constant discovery and for generating test
+//! oracles for the static analyses, not as a full-fidelity emulator.
+
+use crate::intermediate_representation::*;
+use crate::prelude::*;
+
+use std::collections::{BTreeMap, HashMap};
+
+/// The concrete machine state that the interpreter operates on.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    /// The current value of every register that has been written to so far.
+    /// Registers that were never written are treated as undefined.
+    pub registers: HashMap<Variable, Bitvector>,
+    /// A sparse, byte-addressable view of memory.
+    /// Addresses that were never written are treated as undefined.
+    pub memory: BTreeMap<u64, u8>,
+}
+
+/// An error that occurred while interpreting a `Sub`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Execution reached `max_steps` without the subroutine returning.
+    StepBudgetExceeded,
+    /// An indirect branch, indirect call or switch could not be resolved concretely,
+    /// e.g. because the target register is undefined.
+    Unsupported(String),
+    /// A register or memory location was read before it was ever written.
+    UndefinedValue(String),
+    /// A jump or call target `Tid` does not correspond to a block in the interpreted `Sub`.
+    UnknownTarget(Tid),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::StepBudgetExceeded => write!(f, "interpreter step budget exceeded"),
+            Error::Unsupported(msg) => write!(f, "unsupported: {msg}"),
+            Error::UndefinedValue(msg) => write!(f, "undefined value: {msg}"),
+            Error::UnknownTarget(tid) => write!(f, "unknown jump target: {tid}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Result of interpreting a `Sub` to completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionResult {
+    /// The subroutine returned via `Jmp::Return`, or execution ended at a hooked extern
+    /// call without a return site; carries the final state.
+    Returned(State),
+    /// The subroutine called a target (an extern symbol with no registered hook, or a
+    /// local `Sub` the interpreter does not step into) that it therefore cannot model
+    /// the effects of.
+    UnhandledCall { target: Tid, state: State },
+}
+
+/// A pluggable hook for `Call`/`CallInd` targets that resolve to a known `ExternSymbol`.
+///
+/// Implementations model the side effects of library/syscalls on the interpreter
+/// `State`, e.g. writing a fixed return value into the platform's return register.
+/// `CallOther` side effects (syscalls, interrupts) are not passed to the hook; the
+/// interpreter reports them as `Error::Unsupported`.
+pub trait ExternCallHook {
+    /// Apply the effect of calling `symbol` to `state`, or return an error if this
+    /// hook does not know how to handle the symbol.
+    fn call(&mut self, symbol: &ExternSymbol, state: &mut State) -> Result<(), Error>;
+}
+
+/// A hook that does not know how to handle any extern symbol.
+/// Useful as a default when no symbols are expected to be called.
+pub struct NoHook;
+
+impl ExternCallHook for NoHook {
+    fn call(&mut self, symbol: &ExternSymbol, _state: &mut State) -> Result<(), Error> {
+        Err(Error::Unsupported(format!(
+            "no hook registered for extern symbol '{}'",
+            symbol.name
+        )))
+    }
+}
+
+/// Interprets a single `Sub` over an explicit machine state.
+pub struct Interpreter<'a, H: ExternCallHook> {
+    sub: &'a Term<Sub>,
+    extern_symbols: &'a [ExternSymbol],
+    stack_pointer_register: &'a Variable,
+    cpu_architecture: &'a str,
+    hook: H,
+    /// Maximum number of `Def`s/`Jmp`s to execute before giving up with
+    /// `Error::StepBudgetExceeded`. Guarantees termination on loops.
+    max_steps: u64,
+}
+
+impl<'a, H: ExternCallHook> Interpreter<'a, H> {
+    /// Create a new interpreter for `sub`, using `project` for the calling
+    /// convention independent parts of the execution environment (the stack
+    /// pointer register, CPU architecture and the set of known extern symbols).
+    pub fn new(sub: &'a Term<Sub>, project: &'a Project, hook: H, max_steps: u64) -> Self {
+        Interpreter {
+            sub,
+            extern_symbols: &project.program.term.extern_symbols,
+            stack_pointer_register: &project.stack_pointer_register,
+            cpu_architecture: &project.cpu_architecture,
+            hook,
+            max_steps,
+        }
+    }
+
+    /// Whether the CPU architecture this interpreter was built for is little-endian.
+    fn is_little_endian(&self) -> bool {
+        // All architectures currently supported by the Ghidra frontend are little-endian
+        // with the sole exception of big-endian MIPS/PowerPC variants.
+        !(self.cpu_architecture.contains("MIPS") && self.cpu_architecture.ends_with(":BE"))
+            && !self.cpu_architecture.contains("PowerPC")
+    }
+
+    /// Run the subroutine to completion starting at its entry block (`sub.blocks[0]`)
+    /// with the given initial `state`.
+    pub fn run(&mut self, mut state: State) -> Result<ExecutionResult, Error> {
+        let mut block = self
+            .sub
+            .term
+            .blocks
+            .first()
+            .ok_or_else(|| Error::Unsupported("Sub has no blocks".into()))?;
+        let mut steps_remaining = self.max_steps;
+
+        loop {
+            for def in block.term.defs.iter() {
+                if steps_remaining == 0 {
+                    return Err(Error::StepBudgetExceeded);
+                }
+                steps_remaining -= 1;
+                self.step_def(def, &mut state)?;
+            }
+
+            if steps_remaining == 0 {
+                return Err(Error::StepBudgetExceeded);
+            }
+            steps_remaining -= 1;
+
+            match self.step_jmps(&block.term.jmps, &mut state)? {
+                JmpOutcome::Goto(target) => {
+                    block = self.find_block(&target)?;
+                }
+                JmpOutcome::Return => return Ok(ExecutionResult::Returned(state)),
+                JmpOutcome::UnhandledCall(target) => {
+                    return Ok(ExecutionResult::UnhandledCall { target, state })
+                }
+            }
+        }
+    }
+
+    fn find_block(&self, target: &Tid) -> Result<&'a Term<Blk>, Error> {
+        self.sub
+            .term
+            .blocks
+            .iter()
+            .find(|blk| &blk.tid == target)
+            .ok_or_else(|| Error::UnknownTarget(target.clone()))
+    }
+
+    /// Execute a single `Def`, mutating `state` in place.
+    fn step_def(&self, def: &Term<Def>, state: &mut State) -> Result<(), Error> {
+        match &def.term {
+            Def::Assign { var, value } => {
+                let result = self.eval(value, state)?;
+                state.registers.insert(var.clone(), result);
+            }
+            Def::Load { var, address } => {
+                let addr = self.eval(address, state)?;
+                let addr = addr.try_to_u64().map_err(|_| {
+                    Error::Unsupported(format!("Load address does not fit in 64 bits at {}", def.tid))
+                })?;
+                let bytes = self.read_memory(state, addr, var.size)?;
+                state
+                    .registers
+                    .insert(var.clone(), Bitvector::from_bytes(&bytes, self.is_little_endian()));
+            }
+            Def::Store { address, value } => {
+                let addr = self.eval(address, state)?;
+                let addr = addr.try_to_u64().map_err(|_| {
+                    Error::Unsupported(format!(
+                        "Store address does not fit in 64 bits at {}",
+                        def.tid
+                    ))
+                })?;
+                let value = self.eval(value, state)?;
+                self.write_memory(state, addr, &value, self.is_little_endian());
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute the (at most two, or single `Switch`) terminating `Jmp`s of a block.
+    fn step_jmps(&mut self, jmps: &[Term<Jmp>], state: &mut State) -> Result<JmpOutcome, Error> {
+        match jmps {
+            [] => Err(Error::Unsupported("block has no terminating jmp".into())),
+            [only] => self.step_jmp(only, state),
+            [first, second] => match &first.term {
+                Jmp::CBranch { target, condition } => {
+                    if self.eval(condition, state)?.is_zero() {
+                        self.step_jmp(second, state)
+                    } else {
+                        Ok(JmpOutcome::Goto(target.clone()))
+                    }
+                }
+                _ => Err(Error::Unsupported(
+                    "first of two jmps in a block is not a `CBranch`".into(),
+                )),
+            },
+            _ => Err(Error::Unsupported(
+                "block has more than two jmps".into(),
+            )),
+        }
+    }
+
+    fn step_jmp(&mut self, jmp: &Term<Jmp>, state: &mut State) -> Result<JmpOutcome, Error> {
+        match &jmp.term {
+            Jmp::Branch(target) | Jmp::CBranch { target, .. } => Ok(JmpOutcome::Goto(target.clone())),
+            Jmp::BranchInd(expr) => Err(Error::Unsupported(format!(
+                "cannot resolve indirect branch at {}: target is {:?}",
+                jmp.tid,
+                self.eval(expr, state)
+            ))),
+            Jmp::Call { target, return_ } => {
+                if let Some(symbol) = self.extern_symbols.iter().find(|sym| &sym.tid == target) {
+                    match self.hook.call(symbol, state) {
+                        Ok(()) => match return_ {
+                            Some(r) => Ok(JmpOutcome::Goto(r.clone())),
+                            None => Ok(JmpOutcome::Return),
+                        },
+                        Err(_) => Ok(JmpOutcome::UnhandledCall(target.clone())),
+                    }
+                } else {
+                    // `target` is a `Sub` Tid, not a `Blk` Tid of this `Sub` - the
+                    // interpreter only steps through a single subroutine, so it cannot
+                    // resume execution "inside" the callee. Report the call as
+                    // unhandled instead of `Goto`-ing a target `find_block` can never
+                    // resolve.
+                    Ok(JmpOutcome::UnhandledCall(target.clone()))
+                }
+            }
+            Jmp::CallInd { target, .. } => {
+                let addr = self.eval(target, state);
+                Err(Error::Unsupported(format!(
+                    "cannot resolve indirect call at {}: target evaluates to {addr:?}",
+                    jmp.tid
+                )))
+            }
+            Jmp::Return(_) => Ok(JmpOutcome::Return),
+            Jmp::CallOther { description, .. } => Err(Error::Unsupported(format!(
+                "no hook for `CallOther` '{description}' at {}",
+                jmp.tid
+            ))),
+            Jmp::Switch { .. } => Err(Error::Unsupported(format!(
+                "jump tables are not yet interpreted at {}",
+                jmp.tid
+            ))),
+        }
+    }
+
+    fn read_memory(&self, state: &State, address: u64, size: ByteSize) -> Result<Vec<u8>, Error> {
+        let size: u64 = u64::from(size);
+        let mut bytes = Vec::with_capacity(size as usize);
+        for offset in 0..size {
+            let byte = state.memory.get(&(address + offset)).copied().ok_or_else(|| {
+                Error::UndefinedValue(format!("memory at address {:#x} was never written", address + offset))
+            })?;
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+
+    fn write_memory(&self, state: &mut State, address: u64, value: &Bitvector, little_endian: bool) {
+        let mut bytes = value.to_bytes();
+        if !little_endian {
+            bytes.reverse();
+        }
+        for (offset, byte) in bytes.into_iter().enumerate() {
+            state.memory.insert(address + offset as u64, byte);
+        }
+    }
+
+    /// Concretely evaluate an `Expression` against the current `state`.
+    fn eval(&self, expr: &Expression, state: &State) -> Result<Bitvector, Error> {
+        match expr {
+            Expression::Const(bitvector) => Ok(bitvector.clone()),
+            Expression::Var(var) => state.registers.get(var).cloned().ok_or_else(|| {
+                Error::UndefinedValue(format!("register '{}' was never written", var.name))
+            }),
+            Expression::BinOp { op, lhs, rhs } => {
+                let lhs = self.eval(lhs, state)?;
+                let rhs = self.eval(rhs, state)?;
+                Ok(lhs.bin_op(*op, &rhs))
+            }
+            Expression::UnOp { op, arg } => {
+                let arg = self.eval(arg, state)?;
+                Ok(arg.un_op(*op))
+            }
+            Expression::Cast { op, size, arg } => {
+                let arg = self.eval(arg, state)?;
+                Ok(arg.cast(*op, *size))
+            }
+            Expression::Subpiece {
+                low_byte,
+                size,
+                arg,
+            } => {
+                let arg = self.eval(arg, state)?;
+                Ok(arg.subpiece(*low_byte, *size))
+            }
+        }
+    }
+}
+
+/// The effect that executing a block's terminating `Jmp`(s) had on control flow.
+enum JmpOutcome {
+    /// Continue execution at the given intraprocedural target.
+    Goto(Tid),
+    /// The subroutine returned to its caller.
+    Return,
+    /// A `Call` whose effects the interpreter does not model was reached: a call to an
+    /// extern symbol the hook could not handle, or a call to another `Sub`; the
+    /// interpreter stops here.
+    UnhandledCall(Tid),
+}